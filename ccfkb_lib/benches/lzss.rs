@@ -0,0 +1,87 @@
+//! Throughput of the WIPF LZSS codec over large synthetic inputs. Run with `cargo bench --bench lzss`.
+
+use ccfkb_lib::lzss;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5;
+
+/// A small xorshift generator, so the inputs are the same on every run without pulling in a dependency.
+struct XorShift(u64);
+
+impl XorShift {
+	fn next(&mut self) -> u8 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0 as u8
+	}
+}
+
+/// Planar 24 bit "CG" data: smooth gradients with a little noise, like the colour planes of a background image.
+fn synthetic_cg(width: usize, height: usize) -> Vec<u8> {
+	let mut rng = XorShift(0x2545F4914F6CDD1D);
+	let mut out = Vec::with_capacity(width * height * 3);
+	for channel in 0..3 {
+		for y in 0..height {
+			for x in 0..width {
+				let base = (x * (channel + 1) / 7 + y / 3) as u8;
+				let noise = if rng.next() < 24 { rng.next() & 0x07 } else { 0 };
+				out.push(base.wrapping_add(noise));
+			}
+		}
+	}
+	out
+}
+
+fn synthetic_noise(len: usize) -> Vec<u8> {
+	let mut rng = XorShift(0x9E3779B97F4A7C15);
+	(0..len).map(|_| rng.next()).collect()
+}
+
+fn synthetic_flat(len: usize) -> Vec<u8> {
+	(0..len).map(|it| (it / 4096) as u8).collect()
+}
+
+fn throughput(bytes: usize, elapsed: Duration) -> f64 {
+	bytes as f64 * ITERATIONS as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0)
+}
+
+fn bench(name: &str, input: &[u8]) {
+	let start = Instant::now();
+	let mut encoded = vec![];
+	for _ in 0..ITERATIONS {
+		encoded = lzss::encode(black_box(input));
+	}
+	let encode_time = start.elapsed();
+
+	let start = Instant::now();
+	let mut decoded = (vec![], lzss::DecodeStatus::default());
+	for _ in 0..ITERATIONS {
+		decoded = lzss::decode(black_box(&encoded), input.len());
+	}
+	let decode_time = start.elapsed();
+
+	let (decoded, status) = decoded;
+	assert!(decoded == input, "{name}: round trip produced different data");
+	assert_eq!(status, lzss::DecodeStatus { consumed: encoded.len(), produced: input.len() });
+
+	let (_, truncated) = lzss::decode(&encoded[..encoded.len() / 2], input.len());
+	assert!(truncated.produced < input.len(), "{name}: truncated input was not reported");
+
+	println!(
+		"{name:<12} {:>8.2} MiB -> {:>8.2} MiB ({:>5.1}%)   encode {:>8.2} MiB/s   decode {:>8.2} MiB/s",
+		input.len() as f64 / (1024.0 * 1024.0),
+		encoded.len() as f64 / (1024.0 * 1024.0),
+		encoded.len() as f64 * 100.0 / input.len() as f64,
+		throughput(input.len(), encode_time),
+		throughput(input.len(), decode_time),
+	);
+}
+
+fn main() {
+	bench("cg 1280x720", &synthetic_cg(1280, 720));
+	bench("cg 2048x2048", &synthetic_cg(2048, 2048));
+	bench("noise", &synthetic_noise(8 * 1024 * 1024));
+	bench("flat", &synthetic_flat(16 * 1024 * 1024));
+}
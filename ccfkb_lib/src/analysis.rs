@@ -0,0 +1,118 @@
+//! Static analysis of decoded scripts, on top of the opcode table.
+
+use crate::analysis::variables::Variable;
+use crate::opcodes::{Instruction, Opcode};
+use serde_derive::Serialize;
+
+pub mod assets;
+pub mod call_graph;
+pub mod cfg;
+pub mod routes;
+pub mod variables;
+
+/// Set in the branch type of 0x01 when `arg2` is a variable rather than a value.
+const VARIABLE_OPERAND: u8 = 1 << 5;
+
+/// How a 0x01 branch compares its operands, from the low bits of its branch type.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Comparison {
+	Ge,
+	Le,
+	Eq,
+	Ne,
+	Gt,
+	Lt,
+	Unknown(u8),
+}
+
+impl Comparison {
+	pub fn from_branch_type(branch_type: u8) -> Self {
+		match branch_type & !VARIABLE_OPERAND {
+			1 => Comparison::Ge,
+			2 => Comparison::Le,
+			3 => Comparison::Eq,
+			4 => Comparison::Ne,
+			5 => Comparison::Gt,
+			6 => Comparison::Lt,
+			other => Comparison::Unknown(other),
+		}
+	}
+
+	/// Whether `lhs` compares to `rhs` this way, if the comparison is known.
+	pub fn holds(&self, lhs: u16, rhs: u16) -> Option<bool> {
+		match self {
+			Comparison::Ge => Some(lhs >= rhs),
+			Comparison::Le => Some(lhs <= rhs),
+			Comparison::Eq => Some(lhs == rhs),
+			Comparison::Ne => Some(lhs != rhs),
+			Comparison::Gt => Some(lhs > rhs),
+			Comparison::Lt => Some(lhs < rhs),
+			Comparison::Unknown(_) => None,
+		}
+	}
+}
+
+impl std::fmt::Display for Comparison {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Comparison::Ge => write!(f, "GE"),
+			Comparison::Le => write!(f, "LE"),
+			Comparison::Eq => write!(f, "EQ"),
+			Comparison::Ne => write!(f, "NE"),
+			Comparison::Gt => write!(f, "GT"),
+			Comparison::Lt => write!(f, "LT"),
+			Comparison::Unknown(it) => write!(f, "OP_{it:02X}"),
+		}
+	}
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+	Value(u16),
+	Variable(u16),
+}
+
+impl std::fmt::Display for Operand {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Operand::Value(value) => write!(f, "0x{value:04X}"),
+			Operand::Variable(var) => write!(f, "{}", Variable(*var)),
+		}
+	}
+}
+
+/// The condition under which a 0x01 branch is taken: variable `variable` compared with `operand`.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+	pub comparison: Comparison,
+	pub variable: u16,
+	pub operand: Operand,
+}
+
+impl Condition {
+	pub fn from_branch(branch_type: u8, arg1: u16, arg2: u16) -> Self {
+		let operand = if branch_type & VARIABLE_OPERAND != 0 { Operand::Variable(arg2) } else { Operand::Value(arg2) };
+		Condition { comparison: Comparison::from_branch_type(branch_type), variable: arg1, operand }
+	}
+}
+
+impl std::fmt::Display for Condition {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {} {}", Variable(self.variable), self.comparison, self.operand)
+	}
+}
+
+/// The first line of the dialogue an opcode shows, preferring the translation, with the speaker in front.
+pub fn dialogue_line(opcode: &Opcode) -> Option<String> {
+	let first_line = |text: &crate::opcodes::TLString| {
+		let text = text.translation.as_deref().unwrap_or(&text.raw);
+		text.lines().next().unwrap_or_default().trim().to_string()
+	};
+
+	match Instruction::from_opcode(opcode)? {
+		Instruction::Text { text, .. } => Some(first_line(&text)),
+		Instruction::TextWithSpeaker { speaker, text, .. } => Some(format!("{}: {}", first_line(&speaker), first_line(&text))),
+		_ => None,
+	}
+}
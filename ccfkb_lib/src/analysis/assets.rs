@@ -0,0 +1,172 @@
+//! The files scripts load by name, checked against the entries of the game's archives.
+//!
+//! Names without an extension, like the music of 0x21, match an entry of any extension with the same stem. Names are
+//! compared without regard to case.
+
+use crate::opcodes::{Instruction, Opcode, Script};
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+	/// 0x21.
+	Music,
+	/// 0x23 and 0x27.
+	Voice,
+	/// 0x25.
+	SoundEffect,
+	/// 0x43.
+	File,
+	/// 0xB2.
+	Effect,
+	/// 0x46, 0x54, 0x59 and 0xB7, a WIP or MSK image.
+	Image,
+	/// 0x50.
+	Table,
+	/// 0x61.
+	Movie,
+	/// 0xBA, whose string looks like a file name but is not known to be one.
+	Unknown,
+}
+
+impl std::fmt::Display for AssetKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AssetKind::Music => write!(f, "music"),
+			AssetKind::Voice => write!(f, "voice"),
+			AssetKind::SoundEffect => write!(f, "sound effect"),
+			AssetKind::File => write!(f, "file"),
+			AssetKind::Effect => write!(f, "effect"),
+			AssetKind::Image => write!(f, "image"),
+			AssetKind::Table => write!(f, "table"),
+			AssetKind::Movie => write!(f, "movie"),
+			AssetKind::Unknown => write!(f, "unknown"),
+		}
+	}
+}
+
+/// The file `opcode` loads, if any. Empty names, which seem to stop what is playing, are left out.
+pub fn asset_reference(opcode: &Opcode) -> Option<(AssetKind, String)> {
+	let (kind, name) = match Instruction::from_opcode(opcode)? {
+		Instruction::Music { file, .. } => (AssetKind::Music, file),
+		Instruction::Voice { file, .. } | Instruction::Voice2 { file, .. } => (AssetKind::Voice, file),
+		Instruction::SoundEffect { file, .. } => (AssetKind::SoundEffect, file),
+		Instruction::LoadFile { file, .. } => (AssetKind::File, file),
+		Instruction::LoadEffect { file, .. } => (AssetKind::Effect, file),
+		Instruction::LoadImage { file, .. }
+		| Instruction::LoadMsk { file, .. }
+		| Instruction::LoadWip { file, .. }
+		| Instruction::OpB7 { file, .. } => (AssetKind::Image, file),
+		Instruction::LoadTable { file, .. } => (AssetKind::Table, file),
+		Instruction::LoadMovie { file, .. } => (AssetKind::Movie, file),
+		Instruction::OpBA { arg9, .. } => (AssetKind::Unknown, arg9),
+		_ => return None,
+	};
+
+	let name = name.raw.trim();
+	(!name.is_empty()).then(|| (kind, name.to_string()))
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AssetReference {
+	pub script: String,
+	pub address: usize,
+	pub opcode: u8,
+	pub kind: AssetKind,
+	pub name: String,
+	/// The archive entry the name matches, if any. For a name matching several, the first of them.
+	pub entry: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AssetReport {
+	pub references: Vec<AssetReference>,
+	/// Archive entries no script names, leaving out scripts. The engine loads some files without a script naming
+	/// them, so these are only candidates for removal.
+	pub unused: Vec<String>,
+}
+
+fn stem(name: &str) -> &str {
+	name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+impl AssetReport {
+	/// Collects the references of `scripts` and matches them with `entries`, the file names in the archives.
+	pub fn build<'a>(scripts: impl IntoIterator<Item = (&'a str, &'a Script)>, entries: impl IntoIterator<Item = &'a str>) -> Self {
+		let entries: BTreeSet<String> = entries.into_iter().map(str::to_uppercase).collect();
+		let mut by_stem: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+		for entry in &entries {
+			by_stem.entry(stem(entry)).or_default().push(entry);
+		}
+
+		let mut used = BTreeSet::new();
+		let mut references = vec![];
+		for (script, data) in scripts {
+			for opcode in &data.opcodes {
+				let Some((kind, name)) = asset_reference(opcode) else {
+					continue;
+				};
+
+				// A name without an extension may load any of the entries with its stem, like both the WIP and MSK of an
+				// image, so all of them count as used.
+				let upper = name.to_uppercase();
+				let matches: Vec<&str> = if upper.contains('.') {
+					entries.get(&upper).map(String::as_str).into_iter().collect()
+				} else {
+					by_stem.get(upper.as_str()).cloned().unwrap_or_default()
+				};
+				used.extend(matches.iter().map(|it| it.to_string()));
+				let entry = matches.first().copied();
+
+				references.push(AssetReference {
+					script: script.to_string(),
+					address: opcode.address,
+					opcode: opcode.opcode,
+					kind,
+					name,
+					entry: entry.map(str::to_string),
+				});
+			}
+		}
+
+		let unused = entries.iter().filter(|it| !it.ends_with(".WSC") && !used.contains(*it)).cloned().collect();
+		AssetReport { references, unused }
+	}
+
+	pub fn missing(&self) -> impl Iterator<Item = &AssetReference> {
+		self.references.iter().filter(|it| it.entry.is_none())
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// Lists the references of every script, then the missing files and the unused entries.
+	pub fn to_text(&self) -> String {
+		let mut out = String::new();
+		let mut last_script = None;
+		for it in &self.references {
+			if last_script != Some(&it.script) {
+				writeln!(out, "{}", it.script).unwrap();
+				last_script = Some(&it.script);
+			}
+			let entry = it.entry.as_deref().unwrap_or("MISSING");
+			writeln!(out, "    0x{:08X} 0x{:02X} {:<12} {} -> {entry}", it.address, it.opcode, it.kind.to_string(), it.name).unwrap();
+		}
+
+		writeln!(out).unwrap();
+		let missing: Vec<_> = self.missing().collect();
+		writeln!(out, "Missing: {}", missing.len()).unwrap();
+		for it in missing {
+			writeln!(out, "    {} ({} in {} at 0x{:08X})", it.name, it.kind, it.script, it.address).unwrap();
+		}
+		writeln!(out, "Unused: {}", self.unused.len()).unwrap();
+		for it in &self.unused {
+			writeln!(out, "    {it}").unwrap();
+		}
+
+		out
+	}
+}
@@ -0,0 +1,121 @@
+//! The script-to-script graph of a whole archive, following the script names in 0x07 and 0x09.
+
+use crate::opcodes::{Instruction, Script};
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+	/// 0x07, which seems to come back to the calling script.
+	Call,
+	/// 0x09.
+	Goto,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Transition {
+	pub from: String,
+	/// The archive file name of the target script.
+	pub to: String,
+	pub kind: TransitionKind,
+	pub address: usize,
+	/// Whether the archive has the target script.
+	pub exists: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CallGraph {
+	pub scripts: Vec<String>,
+	pub transitions: Vec<Transition>,
+	/// Targets that are not in the archive.
+	pub missing: Vec<String>,
+	/// Scripts no other script goes to. Entry points are among them.
+	pub unreached: Vec<String>,
+}
+
+/// The archive file name of the script a 0x07 or 0x09 names, which leaves out the extension.
+pub fn script_file_name(target: &str) -> String {
+	let target = target.trim().to_uppercase();
+	if target.ends_with(".WSC") { target } else { format!("{target}.WSC") }
+}
+
+impl CallGraph {
+	/// Builds the graph from every script of an archive, by file name.
+	pub fn build<'a>(scripts: impl IntoIterator<Item = (&'a str, &'a Script)>) -> Self {
+		let scripts: Vec<(&str, &Script)> = scripts.into_iter().collect();
+		let names: BTreeMap<String, &str> = scripts.iter().map(|(name, _)| (name.to_uppercase(), *name)).collect();
+
+		let mut transitions = vec![];
+		for (name, script) in &scripts {
+			for opcode in &script.opcodes {
+				let (kind, target) = match Instruction::from_opcode(opcode) {
+					Some(Instruction::CallScript { script, .. }) => (TransitionKind::Call, script),
+					Some(Instruction::GotoScript { script }) => (TransitionKind::Goto, script),
+					_ => continue,
+				};
+
+				let to = script_file_name(&target.raw);
+				let existing = names.get(&to);
+				transitions.push(Transition {
+					from: name.to_string(),
+					to: existing.map(|it| it.to_string()).unwrap_or(to),
+					kind,
+					address: opcode.address,
+					exists: existing.is_some(),
+				});
+			}
+		}
+
+		let missing: BTreeSet<String> = transitions.iter().filter(|it| !it.exists).map(|it| it.to.clone()).collect();
+		let reached: BTreeSet<&str> = transitions.iter().filter(|it| it.from != it.to).map(|it| it.to.as_str()).collect();
+		let unreached = scripts.iter().map(|(name, _)| name.to_string()).filter(|it| !reached.contains(it.as_str())).collect();
+
+		CallGraph {
+			scripts: scripts.iter().map(|(name, _)| name.to_string()).collect(),
+			transitions,
+			missing: missing.into_iter().collect(),
+			unreached,
+		}
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// Writes the graph in Graphviz DOT, with one edge per pair of scripts and kind of transition. Missing targets are
+	/// red and unreached scripts are grey.
+	pub fn to_dot(&self, name: &str) -> String {
+		let mut out = String::new();
+		writeln!(out, "digraph \"{name}\" {{").unwrap();
+		writeln!(out, "\tnode [shape=box, fontname=\"monospace\"];").unwrap();
+
+		for script in &self.scripts {
+			let style = if self.unreached.contains(script) { ", style=filled, fillcolor=lightgrey" } else { "" };
+			writeln!(out, "\t\"{script}\" [label=\"{}\"{style}];", script.trim_end_matches(".WSC")).unwrap();
+		}
+		for script in &self.missing {
+			writeln!(out, "\t\"{script}\" [label=\"{} (missing)\", color=red, style=dashed];", script.trim_end_matches(".WSC")).unwrap();
+		}
+
+		let mut edges: BTreeMap<(&str, &str, TransitionKind), usize> = BTreeMap::new();
+		for it in &self.transitions {
+			*edges.entry((it.from.as_str(), it.to.as_str(), it.kind)).or_default() += 1;
+		}
+		for ((from, to, kind), count) in edges {
+			let mut attributes = vec![];
+			if kind == TransitionKind::Call {
+				attributes.push("style=dashed".to_string());
+			}
+			if count > 1 {
+				attributes.push(format!("label=\"x{count}\""));
+			}
+			let attributes = if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(", ")) };
+			writeln!(out, "\t\"{from}\" -> \"{to}\"{attributes};").unwrap();
+		}
+
+		writeln!(out, "}}").unwrap();
+		out
+	}
+}
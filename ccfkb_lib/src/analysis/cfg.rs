@@ -0,0 +1,203 @@
+//! Control-flow graphs of single scripts, split into basic blocks at jumps, branches, choices and the instructions
+//! that leave the script. Options of a 0x02 whose trailer holds the address of an instruction get an edge there.
+
+use crate::analysis::{dialogue_line, Condition};
+use crate::opcodes::{Choice, Instruction, JumpTarget, Opcode, Script};
+use serde_derive::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// The longest first line of dialogue shown on a block in DOT output, in characters.
+const DOT_LINE_LENGTH: usize = 40;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BasicBlock {
+	pub id: usize,
+	/// The instructions of the block, as indices into `Script::opcodes`, `end` exclusive.
+	pub start: usize,
+	pub end: usize,
+	pub address: usize,
+	pub label: Option<String>,
+	/// The first line of dialogue shown in the block, if any.
+	pub first_line: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EdgeKind {
+	/// The block runs into the next one without jumping.
+	Fallthrough,
+	Jump,
+	/// A 0x01 branch whose condition holds.
+	Taken { condition: Condition },
+	/// A 0x01 branch whose condition does not hold, continuing after it.
+	NotTaken { condition: Condition },
+	/// Continuing after a 0x02 choice, which stores the pick for later branches.
+	Choice { choices: Vec<String> },
+	/// Picking option `option` of a 0x02, going to the instruction at the address in its trailer.
+	Pick { option: usize, choice: String },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Edge {
+	pub from: usize,
+	pub to: usize,
+	#[serde(flatten)]
+	pub kind: EdgeKind,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Cfg {
+	pub blocks: Vec<BasicBlock>,
+	pub edges: Vec<Edge>,
+}
+
+/// Whether no instruction of the script runs after `instruction` in a straight line.
+fn ends_block(instruction: &Instruction) -> bool {
+	matches!(
+		instruction,
+		Instruction::Branch { .. }
+			| Instruction::Choices { .. }
+			| Instruction::Jump { .. }
+			| Instruction::GotoScript { .. }
+			| Instruction::Return { .. }
+			| Instruction::End { .. }
+	)
+}
+
+impl Cfg {
+	pub fn build(script: &Script) -> Self {
+		let opcodes = &script.opcodes;
+		let labels: HashMap<&str, usize> = opcodes
+			.iter()
+			.enumerate()
+			.filter_map(|(idx, it)| Some((it.label.as_deref()?, idx)))
+			.collect();
+		let target_index = |target: &JumpTarget, opcode: &Opcode| match target {
+			JumpTarget::Label(label) => labels.get(label.as_str()).copied(),
+			JumpTarget::Address(address) => {
+				log::warn!("Jump at 0x{:08X} to 0x{address:08X} is not an instruction, leaving it out", opcode.address);
+				None
+			}
+		};
+
+		let index_of: HashMap<usize, usize> = opcodes.iter().enumerate().map(|(idx, it)| (it.address, idx)).collect();
+		let pick_target = |choice: &Choice| index_of.get(&choice.target()?).copied();
+		let choice_text = |choice: &Choice| choice.choice_str.translation.clone().unwrap_or_else(|| choice.choice_str.raw.clone());
+
+		let instructions: Vec<Option<Instruction>> = opcodes.iter().map(Instruction::from_opcode).collect();
+
+		let mut leaders = BTreeSet::from([0]);
+		for (idx, instruction) in instructions.iter().enumerate() {
+			if opcodes[idx].label.is_some() {
+				leaders.insert(idx);
+			}
+			if instruction.as_ref().is_some_and(ends_block) {
+				leaders.insert(idx + 1);
+			}
+			if let Some(Instruction::Choices { choices, .. }) = instruction {
+				leaders.extend(choices.iter().filter_map(pick_target));
+			}
+		}
+		let leaders: Vec<usize> = leaders.into_iter().filter(|it| *it < opcodes.len()).collect();
+
+		let mut blocks = vec![];
+		let mut block_of = vec![0; opcodes.len()];
+		for (id, start) in leaders.iter().copied().enumerate() {
+			let end = leaders.get(id + 1).copied().unwrap_or(opcodes.len());
+			block_of[start..end].fill(id);
+			blocks.push(BasicBlock {
+				id,
+				start,
+				end,
+				address: opcodes[start].address,
+				label: opcodes[start].label.clone(),
+				first_line: opcodes[start..end].iter().find_map(dialogue_line),
+			});
+		}
+
+		let mut edges = vec![];
+		for block in &blocks {
+			let last = block.end - 1;
+			let next = (block.end < opcodes.len()).then(|| block_of[block.end]);
+			let mut add = |to: Option<usize>, kind: EdgeKind| {
+				if let Some(to) = to {
+					edges.push(Edge { from: block.id, to, kind });
+				}
+			};
+
+			match &instructions[last] {
+				Some(Instruction::Branch { branch_type, arg1, arg2, offset, .. }) => {
+					let condition = Condition::from_branch(*branch_type, *arg1, *arg2);
+					let target = target_index(offset, &opcodes[last]).map(|it| block_of[it]);
+					add(target, EdgeKind::Taken { condition });
+					add(next, EdgeKind::NotTaken { condition });
+				}
+				Some(Instruction::Jump { target, .. }) => {
+					add(target_index(target, &opcodes[last]).map(|it| block_of[it]), EdgeKind::Jump);
+				}
+				Some(Instruction::Choices { choices, .. }) => {
+					for (option, choice) in choices.iter().enumerate() {
+						add(pick_target(choice).map(|it| block_of[it]), EdgeKind::Pick { option, choice: choice_text(choice) });
+					}
+					add(next, EdgeKind::Choice { choices: choices.iter().map(choice_text).collect() });
+				}
+				Some(Instruction::GotoScript { .. } | Instruction::Return { .. } | Instruction::End { .. }) => {}
+				_ => add(next, EdgeKind::Fallthrough),
+			}
+		}
+
+		Cfg { blocks, edges }
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// Writes the graph in Graphviz DOT. Blocks show their label, address range and first line of dialogue.
+	pub fn to_dot(&self, name: &str, script: &Script) -> String {
+		let mut out = String::new();
+		writeln!(out, "digraph \"{}\" {{", dot_escape(name)).unwrap();
+		writeln!(out, "\tnode [shape=box, fontname=\"monospace\"];").unwrap();
+
+		for block in &self.blocks {
+			let last = &script.opcodes[block.end - 1];
+			let mut label = vec![];
+			label.extend(block.label.clone());
+			label.push(format!("0x{:08X}..0x{:08X}", block.address, last.address));
+			if let Some(line) = &block.first_line {
+				let mut line: String = line.chars().take(DOT_LINE_LENGTH).collect();
+				if line.chars().count() < block.first_line.as_ref().unwrap().chars().count() {
+					line.push('…');
+				}
+				label.push(line);
+			}
+
+			let label = label.iter().map(|it| dot_escape(it)).collect::<Vec<_>>().join("\\n");
+			writeln!(out, "\tb{} [label=\"{label}\"];", block.id).unwrap();
+		}
+
+		for edge in &self.edges {
+			let attributes = match &edge.kind {
+				EdgeKind::Fallthrough => String::new(),
+				EdgeKind::Jump => " [style=bold]".to_string(),
+				EdgeKind::Taken { condition } => format!(" [label=\"{}\", color=darkgreen]", dot_escape(&condition.to_string())),
+				EdgeKind::NotTaken { condition } => {
+					format!(" [label=\"not {}\", color=red, style=dashed]", dot_escape(&condition.to_string()))
+				}
+				EdgeKind::Choice { choices } => format!(" [label=\"{}\", color=blue]", dot_escape(&choices.join(" / "))),
+				EdgeKind::Pick { option, choice } => {
+					format!(" [label=\"{}. {}\", color=blue, style=bold]", option + 1, dot_escape(choice))
+				}
+			};
+			writeln!(out, "\tb{} -> b{}{attributes};", edge.from, edge.to).unwrap();
+		}
+
+		writeln!(out, "}}").unwrap();
+		out
+	}
+}
+
+fn dot_escape(text: &str) -> String {
+	text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,303 @@
+//! The route tree reached from a starting script: its choices, the scenes each choice leads to and how many choices
+//! deep every route goes, following branches and the scripts named by 0x07 and 0x09.
+//!
+//! An option whose trailer holds the address of an instruction in `arg4` goes there when picked, following the
+//! [`EdgeKind::Pick`] of the CFG. The others go on after the choice, where which branch a pick leads to is only known
+//! when the options of the 0x02 have different `arg1`. Each option is then assumed to store its `arg1` in the
+//! variable compared by the first branch against a value after the choice, which decides that branch and the ones
+//! after it on the same variable until it is written again. Otherwise, and when no option has a target, the options
+//! share one continuation.
+//!
+//! Instructions reached a second time with the same pick are not walked again, so the arms of a branch that join
+//! again are only listed up to where they join. The outline points back to where they were listed.
+
+use crate::analysis::call_graph::script_file_name;
+use crate::analysis::cfg::{Cfg, EdgeKind};
+use crate::analysis::{Condition, Operand};
+use crate::opcodes::{Instruction, Script, TLString};
+use serde_derive::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RouteOption {
+	pub arg1: u16,
+	pub text: TLString,
+	/// What follows the pick, empty when the options of the choice share their continuation.
+	pub nodes: Vec<RouteNode>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "node", rename_all = "snake_case")]
+pub enum RouteNode {
+	/// A 0xE0 scene title.
+	Scene { script: String, address: usize, title: TLString },
+	/// A 0x07, with what the called script shows before returning.
+	Call { script: String, address: usize, target: String, nodes: Vec<RouteNode> },
+	/// A 0x09, after which the route goes on in `target`.
+	Goto { script: String, address: usize, target: String },
+	/// A 0x07 or 0x09 to a script that was not loaded.
+	Missing { script: String, address: usize, target: String },
+	/// A 0x02. When `shared`, the route goes on after the choice whatever is picked.
+	Choice { script: String, address: usize, shared: bool, options: Vec<RouteOption> },
+	/// A 0x01 that could go either way. `taken` is what happens when its condition holds, the route goes on after it
+	/// otherwise. Left out when nothing happens when it holds.
+	Branch { script: String, address: usize, condition: Condition, taken: Vec<RouteNode> },
+	/// A 0xFF, after `depth` choices.
+	End { script: String, address: usize, depth: usize },
+	/// The route goes on as already listed from `address`.
+	Seen { script: String, address: usize },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RouteTree {
+	pub start: String,
+	pub nodes: Vec<RouteNode>,
+}
+
+/// What a choice is assumed to have stored: `value`, in `variable` once a branch compares it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Pick {
+	value: u16,
+	variable: Option<u16>,
+}
+
+struct Walker<'a> {
+	scripts: &'a [(&'a str, &'a Script, Cfg)],
+	by_name: HashMap<String, usize>,
+	/// Blocks walked, as (script, block, pick).
+	walked: HashSet<(usize, usize, Option<Pick>)>,
+}
+
+impl Walker<'_> {
+	fn script_index(&self, target: &TLString) -> Result<usize, String> {
+		let name = script_file_name(&target.raw);
+		self.by_name.get(&name).copied().ok_or(name)
+	}
+
+	/// Walks from `block` of script `script` until the route ends, forks for good or reaches a block already walked
+	/// with the same pick.
+	fn walk(&mut self, mut script: usize, mut block: usize, mut depth: usize, mut pick: Option<Pick>) -> Vec<RouteNode> {
+		let mut nodes = vec![];
+		let scripts = self.scripts;
+		loop {
+			let (name, script_data, cfg) = &scripts[script];
+			let (name, opcodes) = (*name, &script_data.opcodes);
+			let current = &cfg.blocks[block];
+			if !self.walked.insert((script, block, pick)) {
+				nodes.push(RouteNode::Seen { script: name.to_string(), address: current.address });
+				return nodes;
+			}
+
+			let (start, end) = (current.start, current.end);
+			let edge = |matches: fn(&EdgeKind) -> bool| cfg.edges.iter().find(|it| it.from == block && matches(&it.kind)).map(|it| it.to);
+			let fallthrough = edge(|it| !matches!(it, EdgeKind::Taken { .. } | EdgeKind::Pick { .. }));
+			let taken = edge(|it| matches!(it, EdgeKind::Taken { .. }));
+
+			for opcode in &opcodes[start..end] {
+				let address = opcode.address;
+				match Instruction::from_opcode(opcode) {
+					Some(Instruction::SceneTitle { title }) => {
+						nodes.push(RouteNode::Scene { script: name.to_string(), address, title });
+					}
+					Some(Instruction::VarOp { var, .. }) if pick.is_some_and(|it| it.variable == Some(var)) => pick = None,
+					Some(Instruction::CallScript { script: target, .. }) => match self.script_index(&target) {
+						Ok(idx) => {
+							let target = self.scripts[idx].0.to_string();
+							let called = self.walk(idx, 0, depth, None);
+							nodes.push(RouteNode::Call { script: name.to_string(), address, target, nodes: called });
+						}
+						Err(target) => nodes.push(RouteNode::Missing { script: name.to_string(), address, target }),
+					},
+					_ => {}
+				}
+			}
+
+			let last = &opcodes[end - 1];
+			let address = last.address;
+			let next = match Instruction::from_opcode(last) {
+				Some(Instruction::Branch { branch_type, arg1, arg2, .. }) => {
+					let condition = Condition::from_branch(branch_type, arg1, arg2);
+					match decide(&mut pick, &condition) {
+						Some(true) => taken,
+						Some(false) => fallthrough,
+						None => {
+							if let Some(taken) = taken {
+								let taken = self.walk(script, taken, depth, pick);
+								if !taken.is_empty() {
+									nodes.push(RouteNode::Branch { script: name.to_string(), address, condition, taken });
+								}
+							}
+							fallthrough
+						}
+					}
+				}
+				Some(Instruction::Choices { choices, .. }) => {
+					let targets: Vec<Option<usize>> = (0..choices.len())
+						.map(|option| {
+							cfg.edges
+								.iter()
+								.find(|it| it.from == block && matches!(it.kind, EdgeKind::Pick { option: picked, .. } if picked == option))
+								.map(|it| it.to)
+						})
+						.collect();
+					let values: HashSet<u16> = choices.iter().map(|it| it.arg1).collect();
+					let shared = (choices.len() < 2 || values.len() < choices.len()) && targets.iter().all(Option::is_none);
+					let mut options = vec![];
+					for (choice, target) in choices.into_iter().zip(targets) {
+						let pick = Some(Pick { value: choice.arg1, variable: None });
+						let nodes = match target.or(fallthrough) {
+							Some(next) if !shared => self.walk(script, next, depth + 1, pick),
+							_ => vec![],
+						};
+						options.push(RouteOption { arg1: choice.arg1, text: choice.choice_str, nodes });
+					}
+					nodes.push(RouteNode::Choice { script: name.to_string(), address, shared, options });
+					if !shared {
+						return nodes;
+					}
+					depth += 1;
+					fallthrough
+				}
+				Some(Instruction::GotoScript { script: target }) => match self.script_index(&target) {
+					Ok(idx) => {
+						nodes.push(RouteNode::Goto { script: name.to_string(), address, target: self.scripts[idx].0.to_string() });
+						script = idx;
+						pick = None;
+						Some(0)
+					}
+					Err(target) => {
+						nodes.push(RouteNode::Missing { script: name.to_string(), address, target });
+						None
+					}
+				},
+				Some(Instruction::End { .. }) => {
+					nodes.push(RouteNode::End { script: name.to_string(), address, depth });
+					None
+				}
+				Some(Instruction::Return { .. }) => None,
+				_ => fallthrough,
+			};
+
+			match next {
+				Some(next) => block = next,
+				None => return nodes,
+			}
+		}
+	}
+}
+
+/// Decides a branch from the pick of the last choice, if it compares the picked variable with a value. The first
+/// such branch after a choice tells which variable the pick went into.
+fn decide(pick: &mut Option<Pick>, condition: &Condition) -> Option<bool> {
+	let pick = pick.as_mut()?;
+	let Operand::Value(value) = condition.operand else {
+		return None;
+	};
+	let variable = *pick.variable.get_or_insert(condition.variable);
+	if variable != condition.variable {
+		return None;
+	}
+	condition.comparison.holds(pick.value, value)
+}
+
+/// The number of routes, as the ends they reach, and the most choices any of them takes.
+pub fn route_stats(nodes: &[RouteNode]) -> (usize, usize) {
+	let mut routes = 0;
+	let mut deepest = 0;
+	let mut add = |(count, depth): (usize, usize)| {
+		routes += count;
+		deepest = deepest.max(depth);
+	};
+	for node in nodes {
+		match node {
+			RouteNode::End { depth, .. } => add((1, *depth)),
+			RouteNode::Call { nodes, .. } | RouteNode::Branch { taken: nodes, .. } => add(route_stats(nodes)),
+			RouteNode::Choice { options, .. } => options.iter().for_each(|it| add(route_stats(&it.nodes))),
+			_ => {}
+		}
+	}
+	(routes, deepest)
+}
+
+impl RouteTree {
+	/// Walks every route from the start of `start`, one of the file names of `scripts`.
+	pub fn build<'a>(scripts: impl IntoIterator<Item = (&'a str, &'a Script)>, start: &str) -> Option<Self> {
+		let scripts: Vec<(&str, &Script, Cfg)> = scripts
+			.into_iter()
+			.filter(|(_, script)| !script.opcodes.is_empty())
+			.map(|(name, script)| (name, script, Cfg::build(script)))
+			.collect();
+		let by_name: HashMap<String, usize> = scripts.iter().enumerate().map(|(idx, (name, ..))| (name.to_uppercase(), idx)).collect();
+		let start_idx = *by_name.get(&script_file_name(start))?;
+
+		let mut walker = Walker { scripts: &scripts, by_name, walked: HashSet::new() };
+		let nodes = walker.walk(start_idx, 0, 0, None);
+		Some(RouteTree { start: scripts[start_idx].0.to_string(), nodes })
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// Writes the tree as an indented outline, one line per scene, choice option, branch and end of a route.
+	pub fn to_outline(&self) -> String {
+		let mut out = String::new();
+		writeln!(out, "{}", self.start).unwrap();
+		write_outline(&mut out, &self.nodes, 1);
+		let (routes, deepest) = route_stats(&self.nodes);
+		writeln!(out).unwrap();
+		writeln!(out, "{routes} routes, the deepest takes {deepest} choices").unwrap();
+		out
+	}
+}
+
+fn tl_text(text: &TLString) -> String {
+	let raw = text.raw.lines().next().unwrap_or_default().trim();
+	match text.translation.as_deref().map(|it| it.lines().next().unwrap_or_default().trim()) {
+		Some(translation) => format!("{raw} => {translation}"),
+		None => raw.to_string(),
+	}
+}
+
+fn write_outline(out: &mut String, nodes: &[RouteNode], level: usize) {
+	let indent = "    ".repeat(level);
+	for node in nodes {
+		match node {
+			RouteNode::Scene { script, address, title } => {
+				writeln!(out, "{indent}scene {} ({script} 0x{address:08X})", tl_text(title)).unwrap();
+			}
+			RouteNode::Call { script, address, target, nodes } => {
+				writeln!(out, "{indent}calls {target} ({script} 0x{address:08X})").unwrap();
+				write_outline(out, nodes, level + 1);
+			}
+			RouteNode::Goto { script, address, target } => {
+				writeln!(out, "{indent}goes to {target} ({script} 0x{address:08X})").unwrap();
+			}
+			RouteNode::Missing { script, address, target } => {
+				writeln!(out, "{indent}MISSING {target} ({script} 0x{address:08X})").unwrap();
+			}
+			RouteNode::Choice { script, address, shared, options } => {
+				let shared = if *shared { ", every option goes on below" } else { "" };
+				writeln!(out, "{indent}choice ({script} 0x{address:08X}){shared}").unwrap();
+				for (idx, option) in options.iter().enumerate() {
+					let (routes, deepest) = route_stats(&option.nodes);
+					let stats = if option.nodes.is_empty() { String::new() } else { format!(" [{routes} routes, {deepest} choices deep]") };
+					writeln!(out, "{indent}  {}. {}{stats}", idx + 1, tl_text(&option.text)).unwrap();
+					write_outline(out, &option.nodes, level + 1);
+				}
+			}
+			RouteNode::Branch { script, address, condition, taken } => {
+				writeln!(out, "{indent}if {condition} ({script} 0x{address:08X})").unwrap();
+				write_outline(out, taken, level + 1);
+				writeln!(out, "{indent}otherwise").unwrap();
+			}
+			RouteNode::End { script, address, depth } => {
+				writeln!(out, "{indent}END after {depth} choices ({script} 0x{address:08X})").unwrap();
+			}
+			RouteNode::Seen { script, address } => {
+				writeln!(out, "{indent}continues as listed from {script} 0x{address:08X}").unwrap();
+			}
+		}
+	}
+}
@@ -0,0 +1,203 @@
+//! Where scripts write and read the variable table, through 0x03 and the conditions of 0x01.
+//!
+//! Variables can be named in a file with one `address = name` per line, loaded with `--vars=<file>`. Every tool that
+//! shows a variable uses its name once loaded.
+//!
+//! ```text
+//! # Route flags
+//! 0x0123 = affection_misaki
+//! ```
+
+use crate::analysis::Condition;
+use crate::opcodes::{Instruction, Opcode, Script};
+use once_cell::sync::OnceCell;
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+static VARIABLE_NAMES: OnceCell<HashMap<u16, String>> = OnceCell::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableNamesError {
+	Syntax { line: usize, text: String },
+	DuplicateVariable { line: usize, variable: u16 },
+	AlreadyLoaded,
+}
+
+impl std::fmt::Display for VariableNamesError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			VariableNamesError::Syntax { line, text } => write!(f, "line {line}: expected `0x0123 = name`, found {text:?}"),
+			VariableNamesError::DuplicateVariable { line, variable } => {
+				write!(f, "line {line}: variable 0x{variable:04X} is named more than once")
+			}
+			VariableNamesError::AlreadyLoaded => write!(f, "variable names have already been loaded"),
+		}
+	}
+}
+
+impl std::error::Error for VariableNamesError {}
+
+/// Parses a name map. Blank lines and lines starting with `#` are skipped.
+pub fn parse_variable_names(text: &str) -> Result<HashMap<u16, String>, VariableNamesError> {
+	let mut names = HashMap::new();
+	for (idx, line) in text.lines().enumerate() {
+		let line_no = idx + 1;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let syntax = || VariableNamesError::Syntax { line: line_no, text: line.to_string() };
+		let (variable, name) = line.split_once('=').ok_or_else(syntax)?;
+		let variable = variable.trim();
+		let variable = match variable.strip_prefix("0x").or_else(|| variable.strip_prefix("0X")) {
+			Some(hex) => u16::from_str_radix(hex, 16),
+			None => variable.parse(),
+		}
+		.map_err(|_| syntax())?;
+		let name = name.trim();
+		if name.is_empty() || name.contains(char::is_whitespace) {
+			return Err(syntax());
+		}
+
+		if names.insert(variable, name.to_string()).is_some() {
+			return Err(VariableNamesError::DuplicateVariable { line: line_no, variable });
+		}
+	}
+
+	Ok(names)
+}
+
+/// Makes every tool show variables by these names. Can only be done once.
+pub fn set_variable_names(names: HashMap<u16, String>) -> Result<(), VariableNamesError> {
+	VARIABLE_NAMES.set(names).map_err(|_| VariableNamesError::AlreadyLoaded)
+}
+
+pub fn variable_name(variable: u16) -> Option<&'static str> {
+	VARIABLE_NAMES.get()?.get(&variable).map(String::as_str)
+}
+
+/// A variable, shown by its name if it has one and by its address otherwise.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Variable(pub u16);
+
+impl std::fmt::Display for Variable {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match variable_name(self.0) {
+			Some(name) => write!(f, "{name}"),
+			None => write!(f, "var[0x{:04X}]", self.0),
+		}
+	}
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "access", rename_all = "snake_case")]
+pub enum Access {
+	/// Stored to by 0x03, whose operation `op_type` is not fully known yet.
+	Write { op_type: u8, arg2: u8, arg3: u16 },
+	/// Compared in the condition of 0x01.
+	Read { condition: Condition },
+}
+
+/// The variables `opcode` uses and how.
+pub fn variable_uses(opcode: &Opcode) -> Vec<(u16, Access)> {
+	match Instruction::from_opcode(opcode) {
+		Some(Instruction::VarOp { op_type, var, arg2, arg3, .. }) => vec![(var, Access::Write { op_type, arg2, arg3 })],
+		Some(Instruction::Branch { branch_type, arg1, arg2, .. }) => {
+			let condition = Condition::from_branch(branch_type, arg1, arg2);
+			let mut uses = vec![(arg1, Access::Read { condition })];
+			if let crate::analysis::Operand::Variable(other) = condition.operand
+				&& other != arg1
+			{
+				uses.push((other, Access::Read { condition }));
+			}
+			uses
+		}
+		_ => vec![],
+	}
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VariableUse {
+	pub script: String,
+	pub address: usize,
+	#[serde(flatten)]
+	pub access: Access,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VariableSummary {
+	pub variable: u16,
+	pub name: Option<&'static str>,
+	pub writes: Vec<VariableUse>,
+	pub reads: Vec<VariableUse>,
+}
+
+impl VariableSummary {
+	pub fn only_written(&self) -> bool {
+		self.reads.is_empty()
+	}
+
+	pub fn only_read(&self) -> bool {
+		self.writes.is_empty()
+	}
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VariableReport {
+	pub variables: Vec<VariableSummary>,
+}
+
+impl VariableReport {
+	pub fn build<'a>(scripts: impl IntoIterator<Item = (&'a str, &'a Script)>) -> Self {
+		let mut variables: BTreeMap<u16, VariableSummary> = BTreeMap::new();
+		for (name, script) in scripts {
+			for opcode in &script.opcodes {
+				for (variable, access) in variable_uses(opcode) {
+					let summary = variables.entry(variable).or_insert_with(|| VariableSummary {
+						variable,
+						name: variable_name(variable),
+						writes: vec![],
+						reads: vec![],
+					});
+					let uses = match access {
+						Access::Write { .. } => &mut summary.writes,
+						Access::Read { .. } => &mut summary.reads,
+					};
+					uses.push(VariableUse { script: name.to_string(), address: opcode.address, access });
+				}
+			}
+		}
+
+		VariableReport { variables: variables.into_values().collect() }
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// Lists every use of every variable, then the variables that are only written or only read.
+	pub fn to_text(&self) -> String {
+		let mut out = String::new();
+		for summary in &self.variables {
+			writeln!(out, "{} (0x{:04X}): {} writes, {} reads", Variable(summary.variable), summary.variable, summary.writes.len(), summary.reads.len()).unwrap();
+			for it in summary.writes.iter().chain(&summary.reads) {
+				let access = match &it.access {
+					Access::Write { op_type, arg2, arg3 } => format!("write  op 0x{op_type:02X} 0x{arg2:02X} 0x{arg3:04X}"),
+					Access::Read { condition } => format!("read   {condition}"),
+				};
+				writeln!(out, "    {} 0x{:08X} {access}", it.script, it.address).unwrap();
+			}
+		}
+
+		let list = |filter: fn(&VariableSummary) -> bool| {
+			self.variables.iter().filter(|it| filter(it)).map(|it| Variable(it.variable).to_string()).collect::<Vec<_>>().join(", ")
+		};
+		writeln!(out).unwrap();
+		writeln!(out, "Only written: {}", list(VariableSummary::only_written)).unwrap();
+		writeln!(out, "Only read: {}", list(VariableSummary::only_read)).unwrap();
+
+		out
+	}
+}
@@ -0,0 +1,33 @@
+use ccfkb_lib::data::asm::assemble;
+use ccfkb_lib::main_preamble;
+use ccfkb_lib::util::current_dir;
+
+fn main() {
+	let files = main_preamble!(&"WSC.asm");
+
+	let target_dir = current_dir().join("assembled");
+	std::fs::create_dir_all(&target_dir).unwrap();
+	for file in files {
+		log::info!("Assembling file {}", file.file_name().unwrap_or_default());
+		let input = std::fs::read_to_string(&file).unwrap();
+
+		let script = match assemble(&input) {
+			Ok(script) => script,
+			Err(err) => {
+				log::error!("{file}: {err}");
+				std::process::exit(1);
+			}
+		};
+
+		let out = match script.binary_serialise() {
+			Ok(out) => out,
+			Err(err) => {
+				log::error!("{file}: {err}");
+				std::process::exit(1);
+			}
+		};
+
+		let output_file = target_dir.join(file.with_extension("").file_name().unwrap());
+		std::fs::write(output_file, out).unwrap();
+	}
+}
@@ -0,0 +1,36 @@
+use ccfkb_lib::analysis::assets::AssetReport;
+use ccfkb_lib::bin_utils::{flag_value, read_arc_or_exit};
+use ccfkb_lib::data::{decode_archive_scripts, decode_wsc};
+use ccfkb_lib::util::ends_with_ignore_case;
+use ccfkb_lib::main_preamble;
+
+/// Lists the files the scripts in the given archives and WSC files load, as text or with `--json`, and checks them
+/// against the entries of the archives. Exits with 1 when a file is missing.
+fn main() {
+	let files = main_preamble!(&"");
+
+	let mut scripts = vec![];
+	let mut entries = vec![];
+	for file in files {
+		let name = file.file_name().unwrap_or_default().to_string();
+		if ends_with_ignore_case(&name, &"arc") {
+			let mut contents = std::fs::read(&file).unwrap();
+			let (_, _, filenames, data) = read_arc_or_exit(&file, &mut contents[..], file.parent().unwrap_or(&file), None);
+			scripts.extend(decode_archive_scripts(&filenames, &data));
+			entries.extend(filenames);
+		} else if ends_with_ignore_case(&name, &"WSC") {
+			scripts.push((name, decode_wsc(&std::fs::read(&file).unwrap())));
+		}
+	}
+
+	let report = AssetReport::build(scripts.iter().map(|(name, script)| (name.as_str(), script)), entries.iter().map(String::as_str));
+	if flag_value("json").is_some() {
+		println!("{}", report.to_json());
+	} else {
+		print!("{}", report.to_text());
+	}
+
+	if report.missing().next().is_some() {
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,37 @@
+use ccfkb_lib::analysis::call_graph::CallGraph;
+use ccfkb_lib::bin_utils::read_arc_or_exit;
+use ccfkb_lib::data::decode_archive_scripts;
+use ccfkb_lib::util::current_dir;
+use ccfkb_lib::{log, main_preamble};
+
+/// Writes the script-to-script graph of each archive as Graphviz DOT and as JSON, and lists the scripts that are
+/// missing or that nothing goes to.
+fn main() {
+	let files = main_preamble!(&"arc");
+
+	let target_dir = current_dir().join("callgraph");
+	std::fs::create_dir_all(&target_dir).unwrap();
+	for file in files {
+		let archive_name = file.file_name().unwrap();
+		let mut file_contents = std::fs::read(&file).unwrap();
+
+		let (_, _, filenames, data) = read_arc_or_exit(&file, &mut file_contents[..], &target_dir, None);
+		let scripts = decode_archive_scripts(&filenames, &data);
+		if scripts.is_empty() {
+			log::info!("{file} has no scripts.");
+			continue;
+		}
+
+		let graph = CallGraph::build(scripts.iter().map(|(name, script)| (name.as_str(), script)));
+		std::fs::write(target_dir.join(archive_name).with_extension("dot"), graph.to_dot(archive_name)).unwrap();
+		std::fs::write(target_dir.join(archive_name).with_extension("json"), graph.to_json()).unwrap();
+
+		println!("{archive_name}: {} scripts, {} transitions", graph.scripts.len(), graph.transitions.len());
+		for it in graph.transitions.iter().filter(|it| !it.exists) {
+			println!("    missing: {} goes to {} at 0x{:08X}", it.from, it.to, it.address);
+		}
+		for it in &graph.unreached {
+			println!("    unreached: {it}");
+		}
+	}
+}
@@ -0,0 +1,22 @@
+use ccfkb_lib::analysis::cfg::Cfg;
+use ccfkb_lib::data::decode_wsc;
+use ccfkb_lib::main_preamble;
+use ccfkb_lib::util::current_dir;
+
+/// Writes the control-flow graph of each script as Graphviz DOT and as JSON.
+fn main() {
+	let files = main_preamble!(&"WSC");
+
+	let target_dir = current_dir().join("cfg");
+	std::fs::create_dir_all(&target_dir).unwrap();
+	for file in files {
+		let input = std::fs::read(&file).unwrap();
+		let script = decode_wsc(&input);
+		let cfg = Cfg::build(&script);
+
+		let name = file.file_name().unwrap();
+		log::info!("{name} has {} blocks and {} edges", cfg.blocks.len(), cfg.edges.len());
+		std::fs::write(target_dir.join(name).with_extension("WSC.dot"), cfg.to_dot(name, &script)).unwrap();
+		std::fs::write(target_dir.join(name).with_extension("WSC.json"), cfg.to_json()).unwrap();
+	}
+}
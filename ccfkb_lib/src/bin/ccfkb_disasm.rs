@@ -0,0 +1,49 @@
+use ccfkb_lib::bin_utils::flag_value;
+use ccfkb_lib::data::asm::{check_round_trip, disassemble, sample_script};
+use ccfkb_lib::data::decode_wsc;
+use ccfkb_lib::{log, main_preamble};
+use ccfkb_lib::util::current_dir;
+
+fn main() {
+	let files = main_preamble!(&"WSC");
+	let check = flag_value("check").is_some();
+
+	let mut failed = false;
+	if check {
+		match check_round_trip(&sample_script()) {
+			Ok(()) => println!("opcode table: ok"),
+			Err(err) => {
+				println!("opcode table: {err}");
+				failed = true;
+			}
+		}
+	}
+
+	let target_dir = current_dir().join("asm_files");
+	std::fs::create_dir_all(&target_dir).unwrap();
+	for file in files {
+		let input = std::fs::read(&file).unwrap();
+		let script = decode_wsc(&input);
+
+		if check {
+			match check_round_trip(&script) {
+				Ok(()) => println!("{}: ok", file.file_name().unwrap()),
+				Err(err) => {
+					println!("{}: {err}", file.file_name().unwrap());
+					failed = true;
+				}
+			}
+		}
+
+		let output_file = target_dir.join(file.file_name().unwrap()).with_extension("WSC.asm");
+		let text = disassemble(&script).unwrap_or_else(|err| {
+			log::error!("Cannot disassemble {file}: {err}");
+			std::process::exit(1);
+		});
+		std::fs::write(output_file, text).unwrap();
+	}
+
+	if failed {
+		std::process::exit(1);
+	}
+}
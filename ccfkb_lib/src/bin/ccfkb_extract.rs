@@ -1,9 +1,10 @@
 use camino::Utf8PathBuf;
-use ccfkb_lib::data::read_arc;
+use ccfkb_lib::bin_utils::{read_arc_or_exit, wipf_layout_flag};
 use ccfkb_lib::main_preamble;
 
 fn main() {
 	let files = main_preamble!(&"ARC");
+	let wipf_layout = wipf_layout_flag();
 
 	std::fs::create_dir_all("extracted_arcs").unwrap();
 
@@ -13,7 +14,9 @@ fn main() {
 
 		let path = Utf8PathBuf::from("extracted_arcs").join(dirent.file_name().unwrap());
 		std::fs::create_dir_all(&path).unwrap();
-		let (exts, files, filenames, data) = read_arc(&mut file_contents[..], &path, false);
+		// Images go into their own tree so the extracted files can be repacked as they are.
+		let image_path = Utf8PathBuf::from("images").join(dirent.file_name().unwrap());
+		let (exts, files, filenames, data) = read_arc_or_exit(&dirent, &mut file_contents[..], &image_path, wipf_layout);
 
 		let exts_yml_path = path.join("extensions.yml");
 		let exts_yml = serde_yml::to_string(&exts).unwrap();
@@ -0,0 +1,31 @@
+use ccfkb_lib::bin_utils::{flag_value, read_arc_or_exit};
+use ccfkb_lib::data::gallery::{make_thumbnails, write_contact_sheets, write_html_gallery};
+use ccfkb_lib::data::wipf::decode_archive_wipfs;
+use ccfkb_lib::util::current_dir;
+use ccfkb_lib::{log, main_preamble};
+
+fn main() {
+	let files = main_preamble!(&"arc");
+	let contact_sheets = flag_value("contact-sheet").is_some();
+
+	for file in files {
+		let archive_name = file.file_name().unwrap();
+		let mut file_contents = std::fs::read(&file).unwrap();
+		let out_folder = current_dir().join("gallery").join(archive_name);
+
+		let (_, _, filenames, data) = read_arc_or_exit(&file, &mut file_contents[..], &out_folder, None);
+		let wipfs = decode_archive_wipfs(&filenames, &data);
+		if wipfs.is_empty() {
+			log::info!("{file} has no images.");
+			continue;
+		}
+
+		let thumbnails = make_thumbnails(&wipfs);
+		if contact_sheets {
+			write_contact_sheets(&thumbnails, &out_folder);
+		} else {
+			log::info!("Writing {}", out_folder.join("index.html"));
+			write_html_gallery(archive_name, &wipfs, &thumbnails, &out_folder);
+		}
+	}
+}
@@ -0,0 +1,70 @@
+use camino::Utf8PathBuf;
+use ccfkb_lib::bin_utils::flag_value;
+use ccfkb_lib::data::image_diff::{diff_wipf, highlight_diff};
+use ccfkb_lib::data::wipf::{decode_wipf, write_png};
+use ccfkb_lib::{log, main_preamble};
+
+fn main() {
+	let files: Vec<_> = main_preamble!(&"").collect();
+	let diff_dir = flag_value("diff").map(Utf8PathBuf::from);
+
+	let [original_path, patched_path] = &files[..] else {
+		log::error!("Usage: ccfkb_imgdiff <original WIPF> <patched WIPF> [--diff=<folder>]");
+		std::process::exit(1);
+	};
+
+	let decode = |path: &Utf8PathBuf| {
+		decode_wipf(path.file_name().unwrap(), &std::fs::read(path).unwrap()).unwrap_or_else(|err| {
+			log::error!("{path}: {err}");
+			std::process::exit(1);
+		})
+	};
+	let original = decode(original_path);
+	let patched = decode(patched_path);
+
+	let diffs = diff_wipf(&original, &patched).unwrap_or_else(|err| {
+		log::error!("Cannot compare {original_path} and {patched_path}: {err}");
+		std::process::exit(1);
+	});
+
+	if let Some(dir) = &diff_dir {
+		std::fs::create_dir_all(dir).unwrap();
+	}
+
+	for (diff, image) in diffs.iter().zip(&patched) {
+		if diff.is_unchanged() {
+			println!("entry {:03}: unchanged", diff.entry);
+			continue;
+		}
+
+		let total = (diff.width * diff.height) as usize;
+		let mut line = format!("entry {:03}: {}/{} pixels changed", diff.entry, diff.changed_pixels, total);
+		if let Some(bounds) = diff.bounds {
+			line += &format!(
+				", within {}x{} at ({}, {})",
+				bounds.right - bounds.left,
+				bounds.bottom - bounds.top,
+				bounds.left,
+				bounds.top
+			);
+		}
+		if let Some(((x, y), (new_x, new_y))) = diff.moved {
+			line += &format!(", moved from +{x},{y} to +{new_x},{new_y}");
+		}
+		if let Some((depth, new_depth)) = diff.depth {
+			line += &format!(", depth changed from {depth} to {new_depth}");
+		}
+		if !diff.changed_colours.is_empty() {
+			line += &format!(", {} palette colours changed: {:?}", diff.changed_colours.len(), diff.changed_colours);
+		}
+		println!("{line}");
+
+		if let Some(dir) = &diff_dir
+			&& diff.changed_pixels > 0
+		{
+			let out_path = dir.join(format!("{}_{:03}.diff.png", patched_path.file_name().unwrap(), diff.entry));
+			log::info!("Writing {out_path}");
+			write_png(&out_path, diff.width, diff.height, &highlight_diff(diff, image));
+		}
+	}
+}
@@ -0,0 +1,73 @@
+use camino::Utf8PathBuf;
+use ccfkb_lib::bin_utils::flag_value;
+use ccfkb_lib::data::wipf::{convert_wip_depth, decode_wipf, encode_wipf, parse_output_name, read_png_rgba, split_wip_msk};
+use ccfkb_lib::util::current_dir;
+use ccfkb_lib::{log, main_preamble};
+use std::collections::BTreeMap;
+
+fn main() {
+	let files = main_preamble!(&"png");
+	let template_dir = flag_value("template").map(Utf8PathBuf::from);
+
+	let out_dir = current_dir().join("imported_images");
+	std::fs::create_dir_all(&out_dir).unwrap();
+
+	let mut groups: BTreeMap<String, Vec<(usize, u32, u32, Utf8PathBuf)>> = BTreeMap::new();
+	for file in files {
+		let Some((filename, entry_no, x_offset, y_offset)) = parse_output_name(file.file_name().unwrap()) else {
+			log::warn!("Skipping {file}, its name does not carry an entry number and offsets.");
+			continue;
+		};
+		groups.entry(filename.to_string()).or_default().push((entry_no, x_offset, y_offset, file.clone()));
+	}
+
+	for (filename, mut entries) in groups {
+		entries.sort_by_key(|(entry_no, ..)| *entry_no);
+
+		let template = template_dir
+			.as_ref()
+			.map(|dir| dir.join(&filename))
+			.filter(|path| path.is_file())
+			.and_then(|path| {
+				decode_wipf(&filename, &std::fs::read(&path).unwrap())
+					.inspect_err(|err| log::warn!("Ignoring template {path}: {err}"))
+					.ok()
+			});
+
+		let depth = template.as_ref().and_then(|it| it.first()).map_or(24, |it| it.depth);
+		let (wips, msks): (Vec<_>, Vec<_>) = entries
+			.iter()
+			.map(|(entry_no, x_offset, y_offset, path)| {
+				log::info!("Importing {path}");
+				let (width, height, rgba) = read_png_rgba(&std::fs::read(path).unwrap());
+				// Entries past the last one of the template take the palette of its first.
+				let original = template.as_ref().and_then(|it| it.get(*entry_no).or(it.first()));
+				let unk_layer = template
+					.as_ref()
+					.and_then(|it| it.get(*entry_no))
+					.map(|it| it.unk_layer)
+					.unwrap_or_default();
+				let (wip, msk) = split_wip_msk(width, height, &rgba, *x_offset, *y_offset, unk_layer);
+				let palette = original.map(|it| it.palette.as_slice()).unwrap_or_default();
+				(convert_wip_depth(wip, &msk, depth, palette), msk)
+			})
+			.unzip();
+
+		let (stem, ext) = filename.rsplit_once('.').unwrap_or((&filename, "WIP"));
+		let msk_ext = if ext.chars().all(|it| it.is_ascii_lowercase()) { "msk" } else { "MSK" };
+
+		// Without a template every image gets a mask, with one only those whose original has one.
+		let has_msk = match (&template_dir, &template) {
+			(Some(dir), Some(_)) => std::fs::read_dir(dir)
+				.unwrap()
+				.filter_map(|it| it.ok())
+				.any(|it| it.file_name().to_string_lossy().eq_ignore_ascii_case(&format!("{stem}.MSK"))),
+			_ => true,
+		};
+
+		std::fs::write(out_dir.join(&filename), encode_wipf(depth, &wips)).unwrap();
+		if has_msk {
+			std::fs::write(out_dir.join(format!("{stem}.{msk_ext}")), encode_wipf(8, &msks)).unwrap();
+		}
+	}
+}
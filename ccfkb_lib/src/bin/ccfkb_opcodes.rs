@@ -0,0 +1,9 @@
+use ccfkb_lib::main_preamble;
+use ccfkb_lib::opcodes::{opcode_specs, write_opcode_layouts};
+
+/// Prints the opcode layouts in use as a layout file, including any loaded with `--opcodes=<file>`.
+fn main() {
+	let _ = main_preamble!(&"");
+
+	print!("{}", write_opcode_layouts(opcode_specs()));
+}
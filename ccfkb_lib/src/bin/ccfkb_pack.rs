@@ -7,13 +7,20 @@ use ccfkb_lib::opcodes::Script;
 
 fn encode_wsc_file_command(yaml_name_path: &Utf8Path, script: Script) -> Vec<u8> {
 	log::info!("Encoding file {}", yaml_name_path.file_name().unwrap_or_default());
-	let out = script.binary_serialise();
-	out
+	match script.binary_serialise() {
+		Ok(out) => out,
+		Err(err) => {
+			log::error!("Cannot encode {yaml_name_path}: {err}");
+			std::process::exit(1);
+		}
+	}
 }
 
 fn untransform_wsc_file_command(yaml_file: &Utf8Path, yaml_text: &str, text: &str) -> Script {
 	log::info!("Untransforming file {}", &yaml_file.file_name().unwrap_or_default());
 	let mut script: Script = serde_yml::from_str(&yaml_text).unwrap();
+	// YAML written before jumps were labelled still holds their targets as addresses.
+	script.label_jumps();
 	let (_, doclines) = parse_doclines(&text).unwrap();
 
 	tl_reverse_transform_script(&mut script, doclines);
@@ -24,8 +31,11 @@ fn untransform_wsc_file_command(yaml_file: &Utf8Path, yaml_text: &str, text: &st
 fn main() {
 	let files: Vec<_> = main_preamble!(&"WSC.txt").collect();
 	let files = if files.is_empty() {
-		let args = std::env::args().collect::<Vec<_>>();
-		args.iter().skip(1).map(|it| PathBuf::from(it).join("nonexistant")).collect::<Vec<_>>()
+		std::env::args()
+			.skip(1)
+			.filter(|it| !it.starts_with("--"))
+			.map(|it| PathBuf::from(it).join("nonexistant"))
+			.collect::<Vec<_>>()
 	} else {
 		files
 	};
@@ -0,0 +1,95 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use ccfkb_lib::bin_utils::flag_value;
+use ccfkb_lib::data::palette::{apply_palette, palette_from_bgra, parse_palette, write_palette, PaletteFormat};
+use ccfkb_lib::data::wipf::palette_offsets;
+use ccfkb_lib::util::current_dir;
+use ccfkb_lib::{log, main_preamble};
+
+fn palette_name(filename: &str, entry_no: usize) -> String {
+	format!("{filename}_{entry_no:03}")
+}
+
+fn export_palettes(filename: &str, content: &[u8], offsets: &[usize], format: PaletteFormat, out_dir: &Utf8Path) {
+	for (entry_no, offset) in offsets.iter().enumerate() {
+		let name = palette_name(filename, entry_no);
+		let colours = palette_from_bgra(&content[*offset..*offset + 1024]);
+		let out_path = out_dir.join(format!("{name}.{}", format.extension()));
+		log::info!("Writing {out_path}");
+		std::fs::write(out_path, write_palette(&name, &colours, format)).unwrap();
+	}
+}
+
+/// Returns whether any palette was replaced. Nothing is changed if one of the palettes is invalid.
+fn import_palettes(filename: &str, content: &mut [u8], offsets: &[usize], palette_dir: &Utf8Path) -> bool {
+	let mut replacements = vec![];
+	for (entry_no, offset) in offsets.iter().enumerate() {
+		let name = palette_name(filename, entry_no);
+		let Some(path) = [PaletteFormat::Gpl, PaletteFormat::JascPal]
+			.iter()
+			.map(|format| palette_dir.join(format!("{name}.{}", format.extension())))
+			.find(|path| path.is_file())
+		else {
+			continue;
+		};
+
+		let mut palette = content[*offset..*offset + 1024].to_vec();
+		let res = parse_palette(&std::fs::read_to_string(&path).unwrap()).and_then(|colours| apply_palette(&mut palette, &colours));
+		if let Err(err) = res {
+			log::error!("Not importing palettes into {filename}, {path} is invalid: {err}");
+			return false;
+		}
+
+		replacements.push((*offset, palette, path));
+	}
+
+	for (offset, palette, path) in replacements.iter() {
+		log::info!("Importing {path} into {filename}");
+		content[*offset..*offset + 1024].copy_from_slice(palette);
+	}
+
+	!replacements.is_empty()
+}
+
+fn main() {
+	let files = main_preamble!(&"");
+	let format = flag_value("format")
+		.map(|it| {
+			it.parse().unwrap_or_else(|err| {
+				log::error!("{err}");
+				std::process::exit(1);
+			})
+		})
+		.unwrap_or(PaletteFormat::Gpl);
+	let import_dir = flag_value("import").map(Utf8PathBuf::from);
+
+	let out_dir = current_dir().join("palettes");
+	if import_dir.is_none() {
+		std::fs::create_dir_all(&out_dir).unwrap();
+	}
+
+	for file in files {
+		let mut content = std::fs::read(&file).unwrap();
+		if !content.starts_with("WIPF".as_bytes()) {
+			continue;
+		}
+
+		let filename = file.file_name().unwrap();
+		let offsets = match palette_offsets(&content) {
+			Ok(offsets) => offsets,
+			Err(err) => {
+				log::warn!("Skipping {file}: {err}");
+				continue;
+			}
+		};
+
+		match &import_dir {
+			None => export_palettes(filename, &content, &offsets, format, &out_dir),
+			Some(palette_dir) => {
+				// Only the palettes are rewritten, the compressed pixel data is left as it is.
+				if import_palettes(filename, &mut content, &offsets, palette_dir) {
+					std::fs::write(&file, content).unwrap();
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,91 @@
+use ccfkb_lib::bin_utils::{flag_value, load_scripts};
+use ccfkb_lib::interpreter::{Event, Interpreter};
+use ccfkb_lib::opcodes::TLString;
+use ccfkb_lib::{log, main_preamble};
+use std::io::{BufRead, Write};
+
+/// Plays the given scripts or archives in the terminal from `--start=<script>`, showing translations where there are
+/// any. Choices are read from `--choices=1,2,1` first, counting from 1, then asked for.
+fn main() {
+	let files = main_preamble!(&"");
+	let scripts = load_scripts(files);
+
+	let start = match flag_value("start") {
+		Some(start) if !start.is_empty() => start,
+		_ if scripts.len() == 1 => scripts[0].0.clone(),
+		_ => {
+			log::error!("Pass the script to start from with --start=<script>.");
+			std::process::exit(1);
+		}
+	};
+	let Some(mut interpreter) = Interpreter::new(scripts.iter().map(|(name, script)| (name.as_str(), script)), &start) else {
+		log::error!("There is no script named {start}.");
+		std::process::exit(1);
+	};
+
+	let mut scripted = match flag_value("choices") {
+		Some(choices) if !choices.is_empty() => match choices.split(',').map(|it| it.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>() {
+			Ok(choices) => choices,
+			Err(_) => {
+				log::error!("--choices takes option numbers separated by commas, like --choices=1,2,1");
+				std::process::exit(1);
+			}
+		},
+		_ => vec![],
+	}
+	.into_iter();
+	let mut stdin = std::io::stdin().lock().lines();
+
+	loop {
+		let event = interpreter.next_event().unwrap_or_else(|error| {
+			log::error!("{error}");
+			std::process::exit(1);
+		});
+		match event {
+			Event::Text { speaker: Some(speaker), text } => println!("{}: {}", shown(&speaker), shown(&text)),
+			Event::Text { speaker: None, text } => println!("{}", shown(&text)),
+			Event::Scene { title } => println!("\n== {} ==\n", shown(&title)),
+			Event::Choice { options } => {
+				for (idx, option) in options.iter().enumerate() {
+					println!("  {}. {}", idx + 1, shown(&option.choice_str));
+				}
+				loop {
+					let choice = match scripted.next() {
+						Some(choice) => {
+							println!("> {choice}");
+							Some(choice)
+						}
+						None => {
+							print!("> ");
+							std::io::stdout().flush().unwrap();
+							match stdin.next() {
+								Some(Ok(line)) => line.trim().parse::<usize>().ok(),
+								_ => return,
+							}
+						}
+					};
+					let Some(choice) = choice.and_then(|it| it.checked_sub(1)) else {
+						log::error!("Pick an option from 1 to {}.", options.len());
+						continue;
+					};
+					match interpreter.choose(choice) {
+						Ok(()) => break,
+						Err(error) => log::error!("{error}"),
+					}
+				}
+			}
+			Event::End => {
+				println!("\n[end]");
+				let unused = scripted.count();
+				if unused > 0 {
+					log::warn!("The route ended before the last {unused} options of --choices");
+				}
+				return;
+			}
+		}
+	}
+}
+
+fn shown(text: &TLString) -> &str {
+	text.translation.as_deref().unwrap_or(&text.raw)
+}
@@ -0,0 +1,29 @@
+use ccfkb_lib::analysis::call_graph::CallGraph;
+use ccfkb_lib::analysis::routes::RouteTree;
+use ccfkb_lib::bin_utils::{flag_value, load_scripts};
+use ccfkb_lib::{log, main_preamble};
+
+/// Prints the route tree of the given scripts or archives as an outline, or as JSON with `--json`. Walks from
+/// `--start=<script>`, or from every script nothing else goes to.
+fn main() {
+	let files = main_preamble!(&"");
+	let scripts = load_scripts(files);
+	let named = || scripts.iter().map(|(name, script)| (name.as_str(), script));
+
+	let starts = match flag_value("start") {
+		Some(start) if !start.is_empty() => vec![start],
+		_ => CallGraph::build(named()).unreached,
+	};
+
+	for start in starts {
+		let Some(tree) = RouteTree::build(named(), &start) else {
+			log::error!("There is no script named {start}.");
+			std::process::exit(1);
+		};
+		if flag_value("json").is_some() {
+			println!("{}", tree.to_json());
+		} else {
+			println!("{}", tree.to_outline());
+		}
+	}
+}
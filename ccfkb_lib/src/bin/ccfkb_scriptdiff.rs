@@ -0,0 +1,48 @@
+use camino::Utf8PathBuf;
+use ccfkb_lib::bin_utils::{flag_value, read_script_or_exit};
+use ccfkb_lib::data::fix_yaml_str;
+use ccfkb_lib::data::script_diff::{merge_translations, ScriptDiff};
+use ccfkb_lib::{log, main_preamble};
+
+/// Compares two versions of a script, given as WSC or YAML, instruction by instruction, as text or with `--json`.
+///
+/// With `--merge`, takes a base, our and their version instead and merges the translations and notes they changed
+/// into ours, written as YAML to `--out=<file>` or printed. Exits with 1 when they conflict.
+fn main() {
+	let files: Vec<_> = main_preamble!(&"").collect();
+
+	if flag_value("merge").is_some() {
+		let [base, ours, theirs] = &files[..] else {
+			log::error!("Usage: ccfkb_scriptdiff --merge <base> <ours> <theirs> [--out=<file>]");
+			std::process::exit(1);
+		};
+
+		let merge = merge_translations(&read_script_or_exit(base), &read_script_or_exit(ours), &read_script_or_exit(theirs));
+		let yaml = fix_yaml_str(serde_yml::to_string(&merge.script).unwrap());
+		match flag_value("out") {
+			Some(out) => std::fs::write(Utf8PathBuf::from(out), yaml).unwrap(),
+			None => print!("{yaml}"),
+		}
+
+		for conflict in &merge.conflicts {
+			log::error!("Conflict at {conflict}");
+		}
+		log::info!("Merged {} changes from {theirs}, {} conflicts", merge.merged, merge.conflicts.len());
+		if !merge.conflicts.is_empty() {
+			std::process::exit(1);
+		}
+		return;
+	}
+
+	let [old, new] = &files[..] else {
+		log::error!("Usage: ccfkb_scriptdiff <old> <new> [--json]");
+		std::process::exit(1);
+	};
+
+	let diff = ScriptDiff::new(&read_script_or_exit(old), &read_script_or_exit(new));
+	if flag_value("json").is_some() {
+		println!("{}", diff.to_json());
+	} else {
+		print!("{}", diff.to_text());
+	}
+}
@@ -0,0 +1,50 @@
+use ccfkb_lib::bin_utils::{flag_value, read_arc_or_exit};
+use ccfkb_lib::data::stats::{StatsReport, TextStats};
+use ccfkb_lib::data::text_script::{parse_doclines, script_doclines};
+use ccfkb_lib::data::{decode_archive_scripts, decode_wsc};
+use ccfkb_lib::opcodes::Script;
+use ccfkb_lib::util::ends_with_ignore_case;
+use ccfkb_lib::{log, main_preamble};
+
+/// Counts translation progress over archives, WSC files, decoded `.WSC.yaml` scripts and `.WSC.txt` translation
+/// documents. Prints a table, or JSON with `--json`, or a Markdown summary with `--markdown`. Loose files are grouped
+/// by the folder they are in.
+fn main() {
+	let files = main_preamble!(&"");
+
+	let mut scripts = vec![];
+	for file in files {
+		let name = file.file_name().unwrap_or_default().to_string();
+		let folder = file.parent().and_then(|it| it.file_name()).unwrap_or_default().to_string();
+		let script_stats = |script: &Script| TextStats::from_doclines(&script_doclines(script));
+
+		if ends_with_ignore_case(&name, &"arc") {
+			let mut contents = std::fs::read(&file).unwrap();
+			let (_, _, filenames, data) = read_arc_or_exit(&file, &mut contents[..], file.parent().unwrap_or(&file), None);
+			for (script_name, script) in decode_archive_scripts(&filenames, &data) {
+				scripts.push((name.clone(), script_name, script_stats(&script)));
+			}
+		} else if ends_with_ignore_case(&name, &"WSC") {
+			let script = decode_wsc(&std::fs::read(&file).unwrap());
+			scripts.push((folder, name, script_stats(&script)));
+		} else if ends_with_ignore_case(&name, &"WSC.yaml") {
+			let script: Script = serde_yml::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+			scripts.push((folder, name.trim_end_matches(".yaml").to_string(), script_stats(&script)));
+		} else if ends_with_ignore_case(&name, &"WSC.txt") {
+			let text = std::fs::read_to_string(&file).unwrap();
+			match parse_doclines(&text) {
+				Ok((_, doclines)) => scripts.push((folder, name.trim_end_matches(".txt").to_string(), TextStats::from_doclines(&doclines))),
+				Err(error) => log::error!("Could not read {file}: {error}"),
+			}
+		}
+	}
+
+	let report = StatsReport::build(scripts);
+	if flag_value("json").is_some() {
+		println!("{}", report.to_json());
+	} else if flag_value("markdown").is_some() {
+		print!("{}", report.to_markdown());
+	} else {
+		print!("{}", report.to_table());
+	}
+}
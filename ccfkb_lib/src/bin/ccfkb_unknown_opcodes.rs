@@ -0,0 +1,40 @@
+use ccfkb_lib::data::decode_wsc_with_report;
+use ccfkb_lib::main_preamble;
+use std::collections::BTreeMap;
+
+/// Bytes of context shown before an unknown opcode, and at most how many of its raw bytes are shown.
+const CONTEXT_BEFORE: usize = 16;
+const CONTEXT_AFTER: usize = 32;
+
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|it| format!("{it:02X}")).collect::<Vec<_>>().join(" ")
+}
+
+fn main() {
+	let files = main_preamble!(&"WSC");
+
+	let mut counts: BTreeMap<u8, usize> = BTreeMap::new();
+	for file in files {
+		let input = std::fs::read(&file).unwrap();
+		let (_, unknown) = decode_wsc_with_report(&input);
+
+		for it in unknown {
+			*counts.entry(it.opcode).or_default() += 1;
+
+			let before = &input[it.address.saturating_sub(CONTEXT_BEFORE)..it.address];
+			let raw = &input[it.address..it.address + it.length.min(CONTEXT_AFTER)];
+			let ellipsis = if it.length > CONTEXT_AFTER { " .." } else { "" };
+			println!("{} 0x{:08X}: {}, 0x{:X} bytes kept raw", file.file_name().unwrap(), it.address, it.error, it.length);
+			println!("    before: {}", hex(before));
+			println!("    raw:    {}{ellipsis}", hex(raw));
+		}
+	}
+
+	if !counts.is_empty() {
+		println!();
+		println!("opcode  count");
+		for (opcode, count) in counts {
+			println!("0x{opcode:02X}    {count}");
+		}
+	}
+}
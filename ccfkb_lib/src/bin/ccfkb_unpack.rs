@@ -1,5 +1,4 @@
-use ccfkb_lib::bin_utils::{decode_wsc_file_command, transform_wsc_file_command};
-use ccfkb_lib::data::read_arc;
+use ccfkb_lib::bin_utils::{decode_wsc_file_command, read_arc_or_exit, transform_wsc_file_command, wipf_layout_flag};
 use ccfkb_lib::util::current_dir;
 use ccfkb_lib::util::safe_create_dir;
 use ccfkb_lib::{log, main_preamble};
@@ -8,6 +7,7 @@ fn main() {
 	let top_out_path = current_dir().join("extracted_arcs");
 	safe_create_dir(&top_out_path).unwrap();
 	let files: Vec<_> = main_preamble!(&"arc").collect();
+	let wipf_layout = wipf_layout_flag();
 
 	for i in files {
 		let dirent = i;
@@ -21,7 +21,9 @@ fn main() {
 
 		let mut file_contents = std::fs::read(&dirent).unwrap();
 
-		let (exts, files, filenames, data) = read_arc(&mut file_contents[..], &dirent, false);
+		// Images go into their own tree so the extracted files can be repacked as they are.
+		let image_folder = current_dir().join("images").join(dirent.file_name().unwrap());
+		let (exts, files, filenames, data) = read_arc_or_exit(&dirent, &mut file_contents[..], &image_folder, wipf_layout);
 
 		let exts_yml_path = out_folder_base_name.join("extensions.yaml");
 		let exts_yml = serde_yml::to_string(&exts).unwrap();
@@ -0,0 +1,16 @@
+use ccfkb_lib::analysis::variables::VariableReport;
+use ccfkb_lib::bin_utils::{flag_value, load_scripts};
+use ccfkb_lib::main_preamble;
+
+/// Prints where every variable is written and read across the given scripts or archives, as text or with `--json`.
+fn main() {
+	let files = main_preamble!(&"");
+	let scripts = load_scripts(files);
+
+	let report = VariableReport::build(scripts.iter().map(|(name, script)| (name.as_str(), script)));
+	if flag_value("json").is_some() {
+		println!("{}", report.to_json());
+	} else {
+		print!("{}", report.to_text());
+	}
+}
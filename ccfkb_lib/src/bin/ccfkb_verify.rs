@@ -0,0 +1,45 @@
+use ccfkb_lib::bin_utils::read_arc_or_exit;
+use ccfkb_lib::data::verify::verify_wsc;
+use ccfkb_lib::rayon::prelude::*;
+use ccfkb_lib::util::ends_with_ignore_case;
+use ccfkb_lib::{log, main_preamble};
+
+/// Checks that every WSC in the given archives, and every loose WSC file, decodes and encodes back through YAML to
+/// the same bytes. Exits with 1 when one does not.
+fn main() {
+	let files = main_preamble!(&"");
+
+	let mut checked = 0;
+	let mut failed = 0;
+	for file in files {
+		let name = file.file_name().unwrap_or_default().to_string();
+		let mut contents = std::fs::read(&file).unwrap();
+		let results: Vec<(String, _)> = if ends_with_ignore_case(&name, &"arc") {
+			let (_, _, filenames, data) = read_arc_or_exit(&file, &mut contents[..], file.parent().unwrap_or(&file), None);
+			filenames
+				.par_iter()
+				.zip(data.par_iter())
+				.filter(|(name, _)| ends_with_ignore_case(name, &"WSC"))
+				.map(|(script, data)| (format!("{name}/{script}"), verify_wsc(data)))
+				.collect()
+		} else if ends_with_ignore_case(&name, &"WSC") {
+			vec![(file.to_string(), verify_wsc(&contents))]
+		} else {
+			continue;
+		};
+
+		for (script, result) in results {
+			checked += 1;
+			if let Err(error) = result {
+				failed += 1;
+				log::error!("{script}: {error}");
+				println!("FAIL {script}: {error}");
+			}
+		}
+	}
+
+	println!("{checked} scripts checked, {failed} differ");
+	if failed > 0 {
+		std::process::exit(1);
+	}
+}
@@ -1,7 +1,108 @@
 use crate::data::text_script::{parse_doclines, tl_reverse_transform_script, tl_transform_script};
-use crate::data::{decode_wsc, fix_yaml_str};
-use crate::opcodes::Script;
-use camino::Utf8Path;
+use crate::data::wipf::WipfLayout;
+use crate::analysis::variables::{parse_variable_names, set_variable_names};
+use crate::data::{decode_archive_scripts, decode_wsc, fix_yaml_str, read_arc, ArcContents};
+use crate::opcodes::{parse_opcode_layouts, set_opcode_layouts, Script};
+use crate::util::ends_with_ignore_case;
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Looks up a `--name=value` flag on the command line. A bare `--name` yields an empty string.
+pub fn flag_value(name: &str) -> Option<String> {
+	std::env::args().skip(1).find_map(|arg| {
+		let flag = arg.strip_prefix("--")?;
+		match flag.split_once('=') {
+			Some((key, value)) if key == name => Some(value.to_string()),
+			None if flag == name => Some(String::new()),
+			_ => None,
+		}
+	})
+}
+
+/// Reads the `--images=<layout>` flag. A bare `--images` extracts one BMP per entry.
+pub fn wipf_layout_flag() -> Option<WipfLayout> {
+	let value = flag_value("images")?;
+	if value.is_empty() {
+		return Some(WipfLayout::Bmp);
+	}
+
+	match value.parse() {
+		Ok(layout) => Some(layout),
+		Err(err) => {
+			log::error!("{err}");
+			std::process::exit(1);
+		}
+	}
+}
+
+/// Reads the `--opcodes=<file>` flag and loads the opcode layouts in it, exiting if they are invalid.
+pub fn opcode_layouts_flag() {
+	let Some(path) = flag_value("opcodes") else {
+		return;
+	};
+
+	let res = std::fs::read_to_string(&path)
+		.map_err(|err| err.to_string())
+		.and_then(|text| parse_opcode_layouts(&text).map_err(|err| err.to_string()))
+		.and_then(|specs| set_opcode_layouts(specs).map_err(|err| err.to_string()));
+	if let Err(err) = res {
+		log::error!("Cannot load opcode layouts from {path}: {err}");
+		std::process::exit(1);
+	}
+}
+
+/// Reads the `--vars=<file>` flag and loads the variable names in it, exiting if they are invalid.
+pub fn variable_names_flag() {
+	let Some(path) = flag_value("vars") else {
+		return;
+	};
+
+	let res = std::fs::read_to_string(&path)
+		.map_err(|err| err.to_string())
+		.and_then(|text| parse_variable_names(&text).map_err(|err| err.to_string()))
+		.and_then(|names| set_variable_names(names).map_err(|err| err.to_string()));
+	if let Err(err) = res {
+		log::error!("Cannot load variable names from {path}: {err}");
+		std::process::exit(1);
+	}
+}
+
+/// Reads an archive with [`read_arc`], exiting if it is malformed.
+pub fn read_arc_or_exit<'a>(file: &Utf8Path, input: &'a mut [u8], out_folder: &Utf8Path, wipf_layout: Option<WipfLayout>) -> ArcContents<'a> {
+	read_arc(input, out_folder, wipf_layout).unwrap_or_else(|err| {
+		log::error!("Cannot read {file}: {err}");
+		std::process::exit(1);
+	})
+}
+
+/// Decodes the scripts given on the command line, either as WSC files or as every script of an archive.
+pub fn load_scripts(files: impl IntoIterator<Item = Utf8PathBuf>) -> Vec<(String, Script)> {
+	let mut scripts = vec![];
+	for file in files {
+		let name = file.file_name().unwrap_or_default().to_string();
+		if ends_with_ignore_case(&name, &"arc") {
+			let mut contents = std::fs::read(&file).unwrap();
+			let (_, _, filenames, data) = read_arc_or_exit(&file, &mut contents[..], file.parent().unwrap_or(&file), None);
+			scripts.extend(decode_archive_scripts(&filenames, &data));
+		} else if ends_with_ignore_case(&name, &"WSC") {
+			scripts.push((name, decode_wsc(&std::fs::read(&file).unwrap())));
+		}
+	}
+	scripts
+}
+
+/// Reads one script, either a WSC file or its YAML, exiting if the YAML is invalid.
+pub fn read_script_or_exit(file: &Utf8Path) -> Script {
+	let name = file.file_name().unwrap_or_default();
+	if ends_with_ignore_case(&name, &"WSC") {
+		return decode_wsc(&std::fs::read(file).unwrap());
+	}
+
+	let input = std::fs::read_to_string(file).unwrap();
+	serde_yml::from_str(&input).unwrap_or_else(|err| {
+		log::error!("Cannot read {file}: {err}");
+		std::process::exit(1);
+	})
+}
 
 pub fn transform_wsc_file_command(wsc_name_path: &Utf8Path, out_file: &Utf8Path) {
 	log::info!("Transforming file {}", wsc_name_path.file_name().unwrap_or_default());
@@ -29,6 +130,7 @@ pub fn untransform_wsc_file_command(wsc_name_path: &Utf8Path, docline_path: &Utf
 	log::info!("Untransforming file {}", wsc_name_path.file_name().unwrap_or_default());
 	let script_text = std::fs::read_to_string(wsc_name_path).unwrap();
 	let mut script: Script = serde_yml::from_str(&script_text).unwrap();
+	script.label_jumps();
 
 	let docline_text = std::fs::read_to_string(docline_path).unwrap();
 	let (_, doclines) = parse_doclines(&docline_text).unwrap();
@@ -42,9 +144,17 @@ pub fn encode_wsc_file_command(yaml_name_path: &Utf8Path, out_dir_path: &Utf8Pat
 	log::info!("Encoding file {}", yaml_name_path.file_name().unwrap_or_default());
 	let input = std::fs::read_to_string(yaml_name_path).unwrap();
 
-	let script: Script = serde_yml::from_str(&input).unwrap();
+	let mut script: Script = serde_yml::from_str(&input).unwrap();
+	// YAML written before jumps were labelled still holds their targets as addresses.
+	script.label_jumps();
 
-	let out = script.binary_serialise();
+	let out = match script.binary_serialise() {
+		Ok(out) => out,
+		Err(err) => {
+			log::error!("Cannot encode {yaml_name_path}: {err}");
+			std::process::exit(1);
+		}
+	};
 
 	std::fs::write(out_dir_path.join(yaml_name_path.with_extension("").file_name().unwrap()), out).unwrap();
 }
@@ -1,124 +1,207 @@
-use crate::opcodes::{make_opcode, Script};
-use crate::util::{
-  encode_sjis, get_sjis_bytes, get_sjis_bytes_of_length, to_bytes, transmute_to_u32,
-  unwipf,
-};
+use crate::opcodes::{read_opcode, OpField, Opcode, OpcodeError, Script, TLString};
+use crate::util::{encode_sjis, ends_with_ignore_case, get_sjis_bytes, get_sjis_bytes_of_length, transmute_to_u32};
 use camino::Utf8Path as Utf8Path;
 use serde_derive::{Deserialize, Serialize};
 
+pub mod asm;
+pub mod gallery;
+pub mod image_diff;
+pub mod palette;
+pub mod script_diff;
+pub mod stats;
 pub mod text_script;
+pub mod verify;
+pub mod wipf;
 
-#[repr(C, packed)]
-pub struct WIPFHeader {
-	signature: [u8; 4],
-	n_entries: u16,
-	depth: u16,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExtensionDescriptor {
+	pub name: String,
+	pub number: u32,
+	pub offset: u32,
 }
 
-impl WIPFHeader {
-	fn from_ref(slice: &[u8]) -> &Self {
-		if slice.len() < size_of::<Self>() {
-			panic!("bad input slice for wipfheader!");
-		} else {
-			unsafe {
-				let data = slice.as_ptr();
-				&*(data as *const Self)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileDescriptor {
+	pub name: String,
+	pub size: usize,
+	pub offset: usize,
+}
+
+/// libyml panics reading back a plain or quoted scalar with a run of spaces or tabs longer than the room left in its
+/// buffer, which only ever has this much to spare.
+const MAX_BLANK_RUN: usize = 2;
+
+/// Unquotes the hex numbers and byte lists the `serialize_hex_*` and `serialize_inline_ints_*` helpers write, which
+/// YAML would otherwise read back as strings. Text that only looks like them in part is left alone. Strings with a
+/// run of spaces or tabs libyml cannot read back are double-quoted with that run escaped.
+pub fn fix_yaml_str(it: String) -> String {
+	let it = escape_blank_runs(it);
+	let mut out = String::with_capacity(it.len());
+	let mut rest = it.as_str();
+	while let Some(start) = rest.find('\'') {
+		out.push_str(&rest[..start]);
+		rest = &rest[start..];
+		match generated_scalar(rest) {
+			Some((len, unquoted)) => {
+				out.push_str(unquoted);
+				rest = &rest[len..];
+			}
+			None => {
+				out.push('\'');
+				rest = &rest[1..];
 			}
 		}
 	}
+	out.push_str(rest);
+	out
 }
 
-#[repr(C, packed)]
-pub struct BMPHeader {
-	magic: [u8; 2],
-	filesz: u32,
-	res1: u16,
-	res2: u16,
-	offset: u32,
-}
+/// The length of the quoted `'"0x1234"'` or `'[ 0x12, 0x34 ]'` at the start of `input`, and what it unquotes to.
+fn generated_scalar(input: &str) -> Option<(usize, &str)> {
+	let hex_digits = |it: &str| it.bytes().take_while(u8::is_ascii_hexdigit).count();
 
-#[repr(C, packed)]
-pub struct BMPDibV3Header {
-	header_sz: u32,
-	width: u32,
-	height: u32,
-	nplanes: u16,
-	depth: u16,
-	compress_type: u32,
-	bmp_bytesz: u32,
-	hres: u32,
-	vres: u32,
-	ncolors: u32,
-	nimpcolors: u32,
-}
+	if let Some(hex) = input.strip_prefix("'\"0x") {
+		let digits = hex_digits(hex);
+		if digits > 0 && hex[digits..].starts_with("\"'") {
+			let len = 4 + digits + 2;
+			return Some((len, &input[2..len - 2]));
+		}
+		return None;
+	}
 
-#[repr(C, packed)]
-pub struct WIPFENTRY {
-	width: u32,     // unsigned long  width;    // ����
-	height: u32,    // unsigned long  height;   // �߶�
-	x_offset: u32,  // unsigned long  offset_x; // x������ʾλ��
-	y_offset: u32,  // unsigned long  offset_y; // y������ʾλ��
-	unk_layer: u32, // unsigned long  unknown1; // layer?
-	length: u32,    // unsigned long  length;   // �ļ�����
+	let mut list = input.strip_prefix("'[ ")?;
+	if !list.starts_with(" ]'") {
+		loop {
+			let digits = hex_digits(list.strip_prefix("0x")?);
+			if digits == 0 {
+				return None;
+			}
+			list = &list[2 + digits..];
+			match list.strip_prefix(", ") {
+				Some(next) => list = next,
+				None => break,
+			}
+		}
+	}
+	list.strip_prefix(" ]'")?;
+	let len = input.len() - list.len() + 3;
+	Some((len, &input[1..len - 1]))
 }
 
-impl WIPFENTRY {
-	fn from_ref(slice: &[u8]) -> &Self {
-		if slice.len() < size_of::<Self>() {
-			panic!("bad input slice for wipfentry!");
-		} else {
-			unsafe {
-				let data = slice.as_ptr();
-				&*(data as *const Self)
+/// Rewrites every scalar holding more than [`MAX_BLANK_RUN`] spaces or tabs in a row as a double-quoted string with
+/// those runs escaped. The lines of block scalars are left alone, libyml reads them back fine.
+fn escape_blank_runs(yaml: String) -> String {
+	let is_blank = |it: char| it == ' ' || it == '\t';
+	let mut out = String::with_capacity(yaml.len());
+	// The indentation of the line that starts the block scalar being read, if any.
+	let mut block_indent = None;
+	for line in yaml.split_inclusive('\n') {
+		let content = line.trim_end_matches('\n');
+		let indent = content.len() - content.trim_start_matches(' ').len();
+		if let Some(parent) = block_indent {
+			if indent > parent || content.trim().is_empty() {
+				out.push_str(line);
+				continue;
 			}
+			block_indent = None;
+		}
+
+		let start = scalar_start(content);
+		let scalar = &content[start..];
+		if scalar.starts_with(['|', '>']) {
+			block_indent = Some(indent);
+		}
+		if !scalar.split(|it| !is_blank(it)).any(|it| it.len() > MAX_BLANK_RUN) {
+			out.push_str(line);
+			continue;
 		}
-	}
 
-	fn from_ref_as_slice(slice: &[u8], count: usize) -> &[Self] {
-		if slice.len() < (size_of::<Self>() * count) {
-			panic!("Bad input slice for wipf entry array!");
+		let escape = |it: &str| it.replace('\\', "\\\\").replace('"', "\\\"");
+		let body = if let Some(quoted) = scalar.strip_prefix('"').and_then(|it| it.strip_suffix('"')) {
+			quoted.to_string()
+		} else if let Some(quoted) = scalar.strip_prefix('\'').and_then(|it| it.strip_suffix('\'')) {
+			escape(&quoted.replace("''", "'"))
 		} else {
-			unsafe {
-				let data = slice.as_ptr() as *const Self;
-				&*core::ptr::slice_from_raw_parts(data, count)
+			escape(scalar)
+		};
+
+		out.push_str(&content[..start]);
+		out.push('"');
+		let mut rest = body.as_str();
+		while let Some(blank) = rest.find(is_blank) {
+			out.push_str(&rest[..blank]);
+			let run = &rest[blank..rest.len() - rest[blank..].trim_start_matches(is_blank).len()];
+			if run.len() > MAX_BLANK_RUN {
+				run.chars().for_each(|it| out.push_str(if it == ' ' { "\\ " } else { "\\t" }));
+			} else {
+				out.push_str(run);
 			}
+			rest = &rest[blank + run.len()..];
 		}
+		out.push_str(rest);
+		out.push('"');
+		out.push_str(&line[content.len()..]);
 	}
+	out
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ExtensionDescriptor {
-	pub name: String,
-	pub number: u32,
-	pub offset: u32,
+/// Where the scalar on a line of YAML starts, after its indentation, sequence dashes and key.
+fn scalar_start(line: &str) -> usize {
+	let mut rest = line.trim_start_matches(' ');
+	while let Some(item) = rest.strip_prefix("- ") {
+		rest = item.trim_start_matches(' ');
+	}
+	// Scalars with `: ` in them are quoted, so outside quotes it ends a key.
+	if !rest.starts_with(['\'', '"'])
+		&& let Some(colon) = rest.find(": ")
+	{
+		rest = rest[colon + 2..].trim_start_matches(' ');
+	}
+	line.len() - rest.len()
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct FileDescriptor {
-	pub name: String,
-	pub size: usize,
-	pub offset: usize,
+/// Why an archive could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArcError {
+	/// The archive ends before the `needed` bytes at `offset` its header or file table points to.
+	Truncated { offset: usize, needed: usize },
 }
 
-pub fn fix_yaml_str(it: String) -> String {
-	it.replace("'[", "[")
-		.replace("]'", "]")
-		.replace(r#"'""#, "")
-		.replace(r#""'"#, "")
+impl std::fmt::Display for ArcError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ArcError::Truncated { offset, needed } => {
+				write!(f, "archive ends before the 0x{needed:X} bytes at 0x{offset:08X} it says are there")
+			}
+		}
+	}
+}
+
+impl std::error::Error for ArcError {}
+
+fn read_arc_u32(offset: usize, input: &[u8]) -> Result<u32, ArcError> {
+	let bytes = input.get(offset..offset + 4).ok_or(ArcError::Truncated { offset, needed: 4 })?;
+	Ok(transmute_to_u32(0, bytes))
 }
 
-pub fn read_arc<'a>(input: &'a mut [u8], out_folder: &Utf8Path, extract_wipf: bool) -> (Vec<ExtensionDescriptor>, Vec<FileDescriptor>, Vec<String>, Vec<&'a [u8]>) {
-	let n_ext_descriptors = transmute_to_u32(0, input);
+/// The extensions, entries, file names and file contents of an archive.
+pub type ArcContents<'a> = (Vec<ExtensionDescriptor>, Vec<FileDescriptor>, Vec<String>, Vec<&'a [u8]>);
+
+pub fn read_arc<'a>(input: &'a mut [u8], out_folder: &Utf8Path, wipf_layout: Option<wipf::WipfLayout>) -> Result<ArcContents<'a>, ArcError> {
+	let n_ext_descriptors = read_arc_u32(0, input)?;
 
 	let mut ext_descriptors = vec![];
 	let mut curr_idx = 4usize;
 
 	for _ in 0..n_ext_descriptors {
+		if !input.get(curr_idx..).unwrap_or_default().contains(&0) {
+			return Err(ArcError::Truncated { offset: curr_idx, needed: 1 });
+		}
 		let (sjis_bytes, unicode) = get_sjis_bytes(curr_idx, input);
 		curr_idx += sjis_bytes.len();
-		let n_files = transmute_to_u32(curr_idx, input);
+		let n_files = read_arc_u32(curr_idx, input)?;
 		curr_idx += 4;
-		let start_offset = transmute_to_u32(curr_idx, input);
+		let start_offset = read_arc_u32(curr_idx, input)?;
 		curr_idx += 4;
 
 		log::info!(
@@ -134,7 +217,7 @@ pub fn read_arc<'a>(input: &'a mut [u8], out_folder: &Utf8Path, extract_wipf: bo
 
 	log::info!(
     "There are {} files to process.",
-    ext_descriptors.iter().map(|it| it.number).sum::<u32>()
+    ext_descriptors.iter().map(|it| it.number as u64).sum::<u64>()
   );
 
 	let mut filenames = vec![];
@@ -146,9 +229,9 @@ pub fn read_arc<'a>(input: &'a mut [u8], out_folder: &Utf8Path, extract_wipf: bo
 		for _ in 0..ext_descriptor.number {
 			let (name, file_name) = get_sjis_bytes_of_length(descriptor_ptr, 13, input);
 			descriptor_ptr += name.len() - 1;
-			let size = transmute_to_u32(descriptor_ptr, input);
+			let size = read_arc_u32(descriptor_ptr, input)?;
 			descriptor_ptr += 4;
-			let offset = transmute_to_u32(descriptor_ptr, input);
+			let offset = read_arc_u32(descriptor_ptr, input)?;
 			descriptor_ptr += 4;
 			log::debug!(
         "File {file_name}.{} of size 0x{size:08X} starts at 0x{offset:08X}",
@@ -166,33 +249,38 @@ pub fn read_arc<'a>(input: &'a mut [u8], out_folder: &Utf8Path, extract_wipf: bo
 	}
 
 	let mut contents = vec![];
-	let first_offset = files.first().unwrap().offset;
+	let Some(first_offset) = files.first().map(|it| it.offset) else {
+		return Ok((ext_descriptors, files, filenames, contents));
+	};
+	let truncated = |offset: usize, needed: usize| ArcError::Truncated { offset, needed };
 	let mut curr_offset = first_offset;
-	let (_, mut input) = input.split_at_mut(first_offset);
+	let (_, mut input) = input.split_at_mut_checked(first_offset).ok_or(truncated(0, first_offset))?;
 	for (filename, desc) in filenames.iter().zip(&files) {
 		log::info!("Processing {filename}");
 
-		let output_file_path = out_folder.join(filename.as_str());
 		if curr_offset < desc.offset {
 			let diff = desc.offset - curr_offset;
-			(_, input) = input.split_at_mut(diff);
+			(_, input) = input.split_at_mut_checked(diff).ok_or(truncated(curr_offset, diff))?;
+			curr_offset += diff;
 		}
 
-		let (content, new_input) = input.split_at_mut(desc.size); // [desc.offset..(desc.offset + desc.size)];
+		let (content, new_input) = input.split_at_mut_checked(desc.size).ok_or(truncated(curr_offset, desc.size))?; // [desc.offset..(desc.offset + desc.size)];
 		input = new_input;
 		curr_offset += desc.size;
 
 		if filename.ends_with("WSC") {
 			rotate_wsc_for_unpack(content);
-		} else if &content[..4] == "WIPF".as_bytes() && extract_wipf {
-			do_extract_wipf(&filename, &output_file_path, content);
 		}
 
 		// Converts the mut ref back into a normal reference.
 		contents.push(&*content);
 	}
 
-	(ext_descriptors, files, filenames, contents)
+	if let Some(layout) = wipf_layout {
+		wipf::extract_wipfs(&filenames, &contents, out_folder, layout);
+	}
+
+	Ok((ext_descriptors, files, filenames, contents))
 }
 
 pub fn write_arc<T: AsRef<Utf8Path>>(input_files: &[T], extensions: Vec<ExtensionDescriptor>, files: Vec<FileDescriptor>) -> Vec<u8> {
@@ -249,168 +337,71 @@ fn rotate_wsc_for_pack(input: &mut [u8]) {
 	input.iter_mut().for_each(|chr| *chr = chr.rotate_left(2));
 }
 
-fn do_extract_wipf(filename: &str, output_file_path: &Utf8Path, content: &mut [u8]) -> () {
-	let header = WIPFHeader::from_ref(content);
-	let entries =
-		WIPFENTRY::from_ref_as_slice(&content[size_of_val(header)..], header.n_entries as usize);
-
-	log::warn!(
-        "WIPF file {filename} has {} entries with depth {}.",
-        entries.len(),
-        u32::from(header.depth)
-      );
-
-	let data = &content[size_of_val(header) + size_of_val(entries)..];
-	let mut data_ptr = 0usize;
-	for (entry_no, entry) in entries.iter().enumerate() {
-		log::warn!(
-          "    entry is {}x{}",
-          u32::from(entry.width),
-          u32::from(entry.height)
-        );
-
-		let palette = if header.depth == 8 {
-			let palette = &data[data_ptr..data_ptr + 1024];
-			data_ptr += 1024;
-			palette
-		} else {
-			&[]
-		};
-
-		let out_depth = header.depth as u32 / 8;
-		let out_stride = (entry.width * out_depth + 3) & !3u32;
-		let out_len = (entry.height * out_stride) as usize;
-
-		let out_buf = unwipf(&data[data_ptr..(data_ptr + entry.length as usize)], out_len);
-
-		data_ptr += entry.length as usize;
-
-		let out_file = output_file_path.join(&format!(
-			"{filename}_{entry_no:03}+{}x{}y.bmp",
-			u32::from(entry.x_offset),
-			u32::from(entry.y_offset)
-		));
-
-		let mut out_buf = if header.depth == 24 {
-			let mut new_out = vec![0u8; out_buf.len()];
-
-			let clr_stride = entry.width as usize;
-			let clr_len = entry.height as usize * clr_stride;
-
-			for y in 0..(entry.height as usize) {
-				let curr_line_offset = y * clr_stride;
-
-				fn mkrange(start: usize, len: usize) -> std::ops::Range<usize> {
-					start..(start + len)
-				}
-
-				let out_rgb_line = &mut new_out[curr_line_offset..(curr_line_offset + clr_stride)];
-
-				let r_range = mkrange(curr_line_offset, clr_stride);
-				let g_range = mkrange(curr_line_offset + clr_len, clr_stride);
-				let b_range = mkrange(curr_line_offset + clr_len * 2, clr_stride);
-
-				let r_line = &out_buf[r_range];
-				let g_line = &out_buf[g_range];
-				let b_line = &out_buf[b_range];
-
-				for x in 0..entry.width as usize {
-					out_rgb_line[x] = r_line[x];
-					out_rgb_line[x] = g_line[x];
-					out_rgb_line[x] = b_line[x];
-				}
-			}
-
-			new_out
-		} else {
-			out_buf
-		};
-
-		for i in 0..(entry.height / 2) as usize {
-			for j in 0..(entry.width * out_depth) as usize {
-				let a = out_buf
-					[(entry.height as usize - i - 1) * entry.width as usize * out_depth as usize + j];
-				let b = out_buf[i * entry.width as usize * out_depth as usize + j];
-
-				out_buf
-					[(entry.height as usize - i - 1) * entry.width as usize * out_depth as usize + j] = b;
-				out_buf[i * entry.width as usize * out_depth as usize + j] = a;
-			}
-		}
-
-		let (file_size, bmp_offset, imgdata_size) = if header.depth == 8 {
-			(0x436 + out_buf.len(), 0x436, 0x400 + out_buf.len())
-		} else {
-			(0x36 + out_buf.len(), 0x36, out_buf.len())
-		};
-
-		let bmp_header = BMPHeader {
-			magic: ['B' as u8, 'M' as u8],
-			filesz: file_size as u32,
-			res1: 0,
-			res2: 0,
-			offset: bmp_offset,
-		};
+/// How many opcodes in a row have to decode cleanly after an unknown opcode before decoding picks up again.
+const RESYNC_RUN: usize = 4;
+
+/// An opcode `decode_wsc` could not read, kept as raw bytes up to the next point where decoding could resume.
+#[derive(Debug, Clone)]
+pub struct UnknownOpcode {
+	pub address: usize,
+	pub opcode: u8,
+	pub error: OpcodeError,
+	/// The number of bytes kept raw, including the opcode byte.
+	pub length: usize,
+}
 
-		let bmp_dib_header = BMPDibV3Header {
-			header_sz: 0x28,
-			width: entry.width,
-			height: entry.height,
-			nplanes: 1,
-			bmp_bytesz: imgdata_size as u32,
-			depth: header.depth,
-			compress_type: 0,
-			hres: 0,
-			vres: 0,
-			ncolors: 0,
-			nimpcolors: 0,
-		};
+pub fn decode_wsc(input: &[u8]) -> Script {
+	decode_wsc_with_report(input).0
+}
 
-		std::fs::create_dir_all(&output_file_path).unwrap();
-		let hdr_bytes = to_bytes(&bmp_header);
-		let dib_bytes = to_bytes(&bmp_dib_header);
-		std::fs::write(
-			out_file,
-			hdr_bytes
-				.iter()
-				.chain(dib_bytes)
-				.chain(if header.depth == 8 { palette } else { &[] })
-				.chain(out_buf.iter())
-				.copied()
-				.collect::<Vec<u8>>(),
-		)
-			.unwrap();
-	}
+/// Decodes every script of an archive read with [`read_arc`], paired with its file name.
+pub fn decode_archive_scripts(filenames: &[String], contents: &[&[u8]]) -> Vec<(String, Script)> {
+	filenames
+		.iter()
+		.zip(contents)
+		.filter(|(name, _)| ends_with_ignore_case(name, &"WSC"))
+		.map(|(name, content)| (name.clone(), decode_wsc(content)))
+		.collect()
 }
 
-pub fn decode_wsc(input: &[u8]) -> Script {
+/// Decodes a script, also returning every opcode that could not be decoded. Unknown opcodes are kept as raw bytes,
+/// so the script still serialises back to `input` byte for byte. Jump targets are turned into labels.
+pub fn decode_wsc_with_report(input: &[u8]) -> (Script, Vec<UnknownOpcode>) {
 	let mut ptr = 0;
-	let mut ptr_old = 1;
 	let mut opcodes = vec![];
-	let mut at_end = false;
+	let mut unknown = vec![];
 
 	while ptr < input.len() {
-		if ptr_old == ptr {
-			break;
-		}
-
-		let op = make_opcode(&input[ptr..], ptr);
-		if let Some(op) = op {
-			log::debug!(
-        "Got 0x{:02X} of length 0x{:02X} at 0x{:08X}",
-        op.opcode,
-        op.size(),
-        ptr
-      );
-			at_end = op.opcode == 0xFF;
-			ptr += op.size();
-			opcodes.push(op);
-		} else {
-			ptr_old = ptr;
-			log::error!("Unknown opcode at 0x{:08X}", ptr);
-		}
-		if at_end {
-			break;
+		match read_opcode(&input[ptr..], ptr) {
+			Ok(op) => {
+				log::debug!(
+          "Got 0x{:02X} of length 0x{:02X} at 0x{:08X}",
+          op.opcode,
+          op.size(),
+          ptr
+        );
+				let at_end = op.opcode == 0xFF;
+				ptr += op.size();
+				opcodes.push(op);
+				if at_end {
+					break;
+				}
+			}
+			Err(error) => {
+				let length = resync_length(input, ptr);
+				log::error!("{error} at 0x{ptr:08X}, keeping 0x{length:X} bytes as they are");
+
+				let opcode = input[ptr];
+				opcodes.push(Opcode {
+					opcode,
+					address: ptr,
+					actual_address: ptr,
+					label: None,
+					fields: vec![OpField::Raw(input[ptr + 1..ptr + length].to_vec())],
+				});
+				unknown.push(UnknownOpcode { address: ptr, opcode, error, length });
+				ptr += length;
+			}
 		}
 	}
 
@@ -420,11 +411,47 @@ pub fn decode_wsc(input: &[u8]) -> Script {
 		input[ptr..].to_vec()
 	};
 
-	let out = Script {
+	let mut out = Script {
 		opcodes,
 		trailer: rest,
 	};
+	out.label_jumps();
 
-	out
+	(out, unknown)
+}
+
+/// Script text never holds control characters, so strings that do are a sign of decoding from the wrong address.
+fn has_control_chars(op: &Opcode) -> bool {
+	let is_bad = |text: &TLString| text.raw.chars().any(|it| it.is_control());
+	op.fields.iter().any(|field| match field {
+		OpField::String(text) => is_bad(text),
+		OpField::Choice(choices) => choices.iter().any(|it| is_bad(&it.choice_str)),
+		_ => false,
+	})
+}
+
+/// The number of bytes from the unreadable opcode at `start` to the next address where [`RESYNC_RUN`] opcodes in a
+/// row, or every opcode up to the end of the script, decode and serialise back to exactly the same bytes and hold
+/// plausible text.
+fn resync_length(input: &[u8], start: usize) -> usize {
+	let is_clean_run = |mut ptr: usize| {
+		for _ in 0..RESYNC_RUN {
+			if ptr == input.len() {
+				return true;
+			}
+
+			let Ok(op) = read_opcode(&input[ptr..], ptr) else {
+				return false;
+			};
+			let size = op.size();
+			if input.get(ptr..ptr + size) != op.binary_serialise().ok().as_deref() || has_control_chars(&op) {
+				return false;
+			}
+			ptr += size;
+		}
+		true
+	};
+
+	(start + 1..input.len()).find(|it| is_clean_run(*it)).unwrap_or(input.len()) - start
 }
 
@@ -0,0 +1,526 @@
+//! A compact, assembly-style text format for scripts: one instruction per line, its mnemonic followed by its
+//! operands in layout order.
+//!
+//! ```text
+//! L_0x0000000B:
+//!     text 0x1234, 0x07, 0x07, "Hello" => "Translated"    ; 0x000000D0
+//!     branch 0x03, 0x0010, 0x0001, @L_0x0000000B  ; 0x000000E1
+//!     choices 0x02, {0x0001, "Yes", [00 00 00 00 00 00 00 00 00 00 00]}, {0x0001, "No", [...]}
+//!     .unknown 0xF3 [99 41]
+//!     .trailer [01 02 03]
+//! ```
+//!
+//! Padding fields are left out. Strings are written like Rust strings, with an optional `=> "translation"`. Jump
+//! operands refer to the label of an instruction with `@name`, or hold a plain number for targets no instruction
+//! starts at. Anything after a `;` outside a string is a comment; the disassembler puts the address of each
+//! instruction there, and the names of the variables it uses.
+
+use crate::analysis::variables::{variable_name, variable_uses};
+use crate::opcodes::{opcode_spec, opcode_specs, Choice, EncodeError, FieldKind, OpField, Opcode, Script, TLString};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+const COMMENT_COLUMN: usize = 56;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl std::fmt::Display for AsmError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl std::error::Error for AsmError {}
+
+/// Whether the fields of `opcode` follow its layout, so it can be written with its mnemonic.
+fn matches_layout(opcode: &Opcode) -> bool {
+	let Some(spec) = opcode_spec(opcode.opcode) else {
+		return false;
+	};
+
+	spec.fields.len() == opcode.fields.len()
+		&& spec.fields.iter().zip(&opcode.fields).all(|(spec, field)| {
+			matches!(
+				(spec.kind, field),
+				(FieldKind::Byte, OpField::Byte(_))
+					| (FieldKind::Word, OpField::Word(_))
+					| (FieldKind::DWord, OpField::DWord(_))
+					| (FieldKind::String, OpField::String(_))
+					| (FieldKind::Choices, OpField::Choice(_))
+					| (FieldKind::Padding, OpField::Padding(1))
+					| (FieldKind::Jump | FieldKind::RelativeJump, OpField::DWord(_) | OpField::Label(_))
+			)
+		})
+}
+
+fn quote(text: &str) -> String {
+	let mut out = String::from("\"");
+	for ch in text.chars() {
+		match ch {
+			'"' => out += "\\\"",
+			'\\' => out += "\\\\",
+			'\n' => out += "\\n",
+			'\r' => out += "\\r",
+			'\t' => out += "\\t",
+			ch if ch.is_control() => write!(out, "\\u{{{:X}}}", ch as u32).unwrap(),
+			ch => out.push(ch),
+		}
+	}
+	out.push('"');
+	out
+}
+
+fn tl_string(text: &TLString) -> String {
+	match &text.translation {
+		Some(translation) => format!("{} => {}", quote(&text.raw), quote(translation)),
+		None => quote(&text.raw),
+	}
+}
+
+fn byte_list(bytes: &[u8]) -> String {
+	format!("[{}]", bytes.iter().map(|it| format!("{it:02X}")).collect::<Vec<_>>().join(" "))
+}
+
+/// Fails for an opcode that does not follow its layout and cannot be written as bytes either.
+fn disassemble_opcode(opcode: &Opcode) -> Result<String, EncodeError> {
+	if !matches_layout(opcode) {
+		let bytes = opcode.binary_serialise()?;
+		return Ok(format!(".unknown 0x{:02X} {}", opcode.opcode, byte_list(&bytes[1..])));
+	}
+
+	let spec = opcode_spec(opcode.opcode).unwrap();
+	let operands: Vec<String> = opcode
+		.fields
+		.iter()
+		.filter_map(|field| match field {
+			OpField::Label(label) => Some(format!("@{label}")),
+			OpField::Byte(value) => Some(format!("0x{value:02X}")),
+			OpField::Word(value) => Some(format!("0x{value:04X}")),
+			OpField::DWord(value) => Some(format!("0x{value:08X}")),
+			OpField::String(text) => Some(tl_string(text)),
+			OpField::Choice(choices) => Some(
+				choices
+					.iter()
+					.map(|it| format!("{{0x{:04X}, {}, {}}}", it.arg1, tl_string(&it.choice_str), byte_list(&it.trailer)))
+					.collect::<Vec<_>>()
+					.join(", "),
+			),
+			OpField::Padding(_) | OpField::Raw(_) => None,
+		})
+		.filter(|it| !it.is_empty())
+		.collect();
+
+	if operands.is_empty() {
+		Ok(spec.mnemonic.to_string())
+	} else {
+		Ok(format!("{} {}", spec.mnemonic, operands.join(", ")))
+	}
+}
+
+/// Writes a script in the assembly format. Notes on strings are not kept.
+pub fn disassemble(script: &Script) -> Result<String, EncodeError> {
+	let mut out = String::new();
+	for opcode in &script.opcodes {
+		if let Some(label) = &opcode.label {
+			writeln!(out, "{label}:").unwrap();
+		}
+
+		let line = disassemble_opcode(opcode)?;
+		let padding = COMMENT_COLUMN.saturating_sub(line.chars().count());
+		let mut names: Vec<&str> = variable_uses(opcode).iter().filter_map(|(it, _)| variable_name(*it)).collect();
+		names.dedup();
+		let names: String = names.iter().map(|it| format!(" {it}")).collect();
+		writeln!(out, "\t{line}{:padding$}\t; 0x{:08X}{names}", "", opcode.address).unwrap();
+	}
+
+	if !script.trailer.is_empty() {
+		writeln!(out, "\t.trailer {}", byte_list(&script.trailer)).unwrap();
+	}
+
+	Ok(out)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Number(u64),
+	Str(String),
+	Label(String),
+	Bytes(Vec<u8>),
+	Directive(String),
+	Word(String),
+	Colon,
+	Comma,
+	Arrow,
+	LBrace,
+	RBrace,
+}
+
+fn tokenise(line: &str) -> Result<Vec<Token>, String> {
+	let chars: Vec<char> = line.chars().collect();
+	let mut tokens = vec![];
+	let mut idx = 0;
+
+	let is_word_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+	let read_word = |idx: &mut usize| {
+		let start = *idx;
+		while *idx < chars.len() && is_word_char(chars[*idx]) {
+			*idx += 1;
+		}
+		chars[start..*idx].iter().collect::<String>()
+	};
+
+	while idx < chars.len() {
+		let ch = chars[idx];
+		match ch {
+			';' => break,
+			ch if ch.is_whitespace() => idx += 1,
+			',' => {
+				tokens.push(Token::Comma);
+				idx += 1;
+			}
+			':' => {
+				tokens.push(Token::Colon);
+				idx += 1;
+			}
+			'{' => {
+				tokens.push(Token::LBrace);
+				idx += 1;
+			}
+			'}' => {
+				tokens.push(Token::RBrace);
+				idx += 1;
+			}
+			'=' if chars.get(idx + 1) == Some(&'>') => {
+				tokens.push(Token::Arrow);
+				idx += 2;
+			}
+			'@' => {
+				idx += 1;
+				tokens.push(Token::Label(read_word(&mut idx)));
+			}
+			'.' => {
+				idx += 1;
+				tokens.push(Token::Directive(read_word(&mut idx)));
+			}
+			'[' => {
+				let end = chars[idx..].iter().position(|it| *it == ']').ok_or("unterminated byte list")? + idx;
+				let text: String = chars[idx + 1..end].iter().collect();
+				let bytes = text
+					.split_whitespace()
+					.map(|it| u8::from_str_radix(it, 16).map_err(|_| format!("bad byte {it:?}")))
+					.collect::<Result<Vec<_>, _>>()?;
+				tokens.push(Token::Bytes(bytes));
+				idx = end + 1;
+			}
+			'"' => {
+				idx += 1;
+				let mut text = String::new();
+				loop {
+					let ch = *chars.get(idx).ok_or("unterminated string")?;
+					idx += 1;
+					match ch {
+						'"' => break,
+						'\\' => {
+							let escaped = *chars.get(idx).ok_or("unterminated string")?;
+							idx += 1;
+							match escaped {
+								'n' => text.push('\n'),
+								'r' => text.push('\r'),
+								't' => text.push('\t'),
+								'"' | '\\' => text.push(escaped),
+								'u' => {
+									let end = chars[idx..].iter().position(|it| *it == '}').ok_or("bad \\u escape")? + idx;
+									let code: String = chars[idx + 1..end].iter().collect();
+									let ch = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32).ok_or("bad \\u escape")?;
+									text.push(ch);
+									idx = end + 1;
+								}
+								other => return Err(format!("unknown escape \\{other}")),
+							}
+						}
+						ch => text.push(ch),
+					}
+				}
+				tokens.push(Token::Str(text));
+			}
+			ch if ch.is_ascii_digit() => {
+				let word = read_word(&mut idx);
+				let value = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+					Some(hex) => u64::from_str_radix(hex, 16),
+					None => word.parse(),
+				}
+				.map_err(|_| format!("bad number {word:?}"))?;
+				tokens.push(Token::Number(value));
+			}
+			ch if is_word_char(ch) => tokens.push(Token::Word(read_word(&mut idx))),
+			other => return Err(format!("unexpected character {other:?}")),
+		}
+	}
+
+	Ok(tokens)
+}
+
+struct Operands {
+	tokens: std::vec::IntoIter<Token>,
+}
+
+impl Operands {
+	fn next(&mut self, what: &str) -> Result<Token, String> {
+		self.tokens.next().ok_or_else(|| format!("expected {what}"))
+	}
+
+	fn number(&mut self, max: u64) -> Result<u64, String> {
+		match self.next("a number")? {
+			Token::Number(value) if value <= max => Ok(value),
+			Token::Number(value) => Err(format!("0x{value:X} does not fit in 0x{max:X}")),
+			other => Err(format!("expected a number, found {other:?}")),
+		}
+	}
+
+	fn string(&mut self) -> Result<TLString, String> {
+		let Token::Str(raw) = self.next("a string")? else {
+			return Err("expected a string".to_string());
+		};
+
+		let mut translation = None;
+		if self.tokens.as_slice().first() == Some(&Token::Arrow) {
+			self.tokens.next();
+			let Token::Str(text) = self.next("a translation")? else {
+				return Err("expected a translation after =>".to_string());
+			};
+			translation = Some(text);
+		}
+
+		Ok(TLString { raw, translation, notes: None })
+	}
+
+	fn bytes(&mut self) -> Result<Vec<u8>, String> {
+		match self.next("a byte list")? {
+			Token::Bytes(bytes) => Ok(bytes),
+			other => Err(format!("expected a byte list, found {other:?}")),
+		}
+	}
+
+	fn expect(&mut self, token: Token) -> Result<(), String> {
+		let found = self.next(&format!("{token:?}"))?;
+		if found == token { Ok(()) } else { Err(format!("expected {token:?}, found {found:?}")) }
+	}
+
+	fn choice(&mut self) -> Result<Choice, String> {
+		self.expect(Token::LBrace)?;
+		let arg1 = self.number(u16::MAX as u64)? as u16;
+		self.expect(Token::Comma)?;
+		let choice_str = self.string()?;
+		self.expect(Token::Comma)?;
+		let trailer = self.bytes()?;
+		self.expect(Token::RBrace)?;
+		Ok(Choice { arg1, choice_str, trailer })
+	}
+
+	fn comma(&mut self) -> Result<(), String> {
+		self.expect(Token::Comma)
+	}
+
+	fn finish(mut self) -> Result<(), String> {
+		match self.tokens.next() {
+			None => Ok(()),
+			Some(extra) => Err(format!("unexpected {extra:?} after the operands")),
+		}
+	}
+}
+
+/// Parses the assembly format back into a script. Instructions get fresh addresses from the start of the script, so
+/// lines can be added or removed as long as jumps use labels.
+pub fn assemble(text: &str) -> Result<Script, AsmError> {
+	let mut opcodes: Vec<Opcode> = vec![];
+	let mut trailer = vec![];
+	let mut labels: HashSet<String> = HashSet::new();
+	let mut label: Option<(usize, String)> = None;
+	let mut references = vec![];
+	let mut address = 0usize;
+
+	for (line_idx, line) in text.lines().enumerate() {
+		let line_no = line_idx + 1;
+		let error = |message: String| AsmError { line: line_no, message };
+
+		let mut tokens = tokenise(line).map_err(error)?;
+		if let [Token::Word(name), Token::Colon, ..] = &tokens[..] {
+			if !labels.insert(name.clone()) {
+				return Err(error(format!("label {name} is defined twice")));
+			}
+			if let Some((_, other)) = &label {
+				return Err(error(format!("label {name} is on the same instruction as {other}")));
+			}
+			label = Some((line_no, name.clone()));
+			tokens.drain(..2);
+		}
+
+		let mut operands = Operands { tokens: tokens.into_iter() };
+		let opcode = match operands.tokens.next() {
+			None => continue,
+			Some(Token::Directive(directive)) if directive == "trailer" => {
+				trailer = operands.bytes().map_err(error)?;
+				operands.finish().map_err(error)?;
+				continue;
+			}
+			Some(Token::Directive(directive)) if directive == "unknown" => {
+				let opcode = operands.number(u8::MAX as u64).map_err(error)? as u8;
+				let raw = operands.bytes().map_err(error)?;
+				operands.finish().map_err(error)?;
+				Opcode { opcode, address, actual_address: address, label: None, fields: vec![OpField::Raw(raw)] }
+			}
+			Some(Token::Word(mnemonic)) => {
+				let spec = opcode_specs()
+					.find(|it| it.mnemonic == mnemonic)
+					.ok_or_else(|| error(format!("unknown mnemonic {mnemonic}")))?;
+
+				let mut fields = vec![];
+				let mut first = true;
+				for field in spec.fields {
+					if field.kind == FieldKind::Padding {
+						fields.push(OpField::Padding(1));
+						continue;
+					}
+					if !first {
+						operands.comma().map_err(error)?;
+					}
+					first = false;
+
+					let value = match field.kind {
+						FieldKind::Jump | FieldKind::RelativeJump
+							if matches!(operands.tokens.as_slice().first(), Some(Token::Label(_))) =>
+						{
+							let Some(Token::Label(target)) = operands.tokens.next() else { unreachable!() };
+							references.push((line_no, target.clone()));
+							OpField::Label(target)
+						}
+						FieldKind::Jump | FieldKind::RelativeJump => {
+							OpField::DWord(operands.number(u32::MAX as u64).map_err(error)? as u32)
+						}
+						FieldKind::Byte => OpField::Byte(operands.number(u8::MAX as u64).map_err(error)? as u8),
+						FieldKind::Word => OpField::Word(operands.number(u16::MAX as u64).map_err(error)? as u16),
+						FieldKind::DWord => OpField::DWord(operands.number(u32::MAX as u64).map_err(error)? as u32),
+						FieldKind::String => OpField::String(operands.string().map_err(error)?),
+						FieldKind::Choices => {
+							let n_choices = fields.iter().find_map(|it| match it {
+								OpField::Byte(it) => Some(*it),
+								_ => None,
+							});
+							let mut choices = vec![];
+							for choice_idx in 0..n_choices.unwrap_or_default() {
+								if choice_idx > 0 {
+									operands.comma().map_err(error)?;
+								}
+								choices.push(operands.choice().map_err(error)?);
+							}
+							OpField::Choice(choices)
+						}
+						FieldKind::Padding => unreachable!(),
+					};
+					fields.push(value);
+				}
+				operands.finish().map_err(error)?;
+
+				Opcode { opcode: spec.opcode, address, actual_address: address, label: None, fields }
+			}
+			Some(other) => return Err(error(format!("expected a mnemonic, found {other:?}"))),
+		};
+
+		address += opcode.size();
+		opcodes.push(Opcode { label: label.take().map(|(_, name)| name), ..opcode });
+	}
+
+	if let Some((line, name)) = label {
+		return Err(AsmError { line, message: format!("label {name} has no instruction after it") });
+	}
+	if let Some((line, target)) = references.into_iter().find(|(_, target)| !labels.contains(target)) {
+		return Err(AsmError { line, message: format!("unknown label {target}") });
+	}
+
+	Ok(Script { opcodes, trailer })
+}
+
+/// Disassembles and reassembles `script`, checking that it still serialises to the same bytes.
+pub fn check_round_trip(script: &Script) -> Result<(), String> {
+	let text = disassemble(script).map_err(|err| err.to_string())?;
+	let reassembled = assemble(&text).map_err(|err| err.to_string())?;
+
+	let expected = script.binary_serialise().map_err(|err| err.to_string())?;
+	let actual = reassembled.binary_serialise().map_err(|err| err.to_string())?;
+	match expected.iter().zip(&actual).position(|(a, b)| a != b) {
+		None if expected.len() == actual.len() => Ok(()),
+		None => Err(format!("reassembled script is 0x{:X} bytes, expected 0x{:X}", actual.len(), expected.len())),
+		Some(offset) => Err(format!("reassembled script differs at 0x{offset:08X}")),
+	}
+}
+
+/// A script holding a sample of every opcode in the table, with its jumps pointing at real instructions, an unknown
+/// opcode, a translated string and a trailer.
+pub fn sample_script() -> Script {
+	let mut opcodes: Vec<Opcode> = vec![];
+	let mut address = 0;
+	for spec in opcode_specs().filter(|it| it.opcode != 0xFF) {
+		let opcode = spec.sample(address);
+		address += opcode.size();
+		opcodes.push(opcode);
+	}
+
+	opcodes.push(Opcode {
+		opcode: 0xF3,
+		address,
+		actual_address: address,
+		label: None,
+		fields: vec![OpField::Raw(vec![0x99, 0x41])],
+	});
+	address += 3;
+	let end = opcode_spec(0xFF).map(|it| it.sample(address));
+	opcodes.extend(end);
+
+	for (idx, opcode) in opcodes.iter_mut().enumerate() {
+		if let Some(OpField::String(text)) = opcode.fields.iter_mut().find(|it| matches!(it, OpField::String(_))) {
+			text.translation = Some(format!("Translated\tline {idx}"));
+		}
+	}
+
+	let mut script = Script { opcodes, trailer: vec![0x01, 0x02, 0x03] };
+	script.label_jumps();
+	script
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sample_script_round_trips() {
+		assert_eq!(check_round_trip(&sample_script()), Ok(()));
+	}
+
+	#[test]
+	fn every_opcode_round_trips() {
+		for spec in opcode_specs() {
+			let opcode = spec.sample(0);
+			let mut opcodes = vec![opcode.clone()];
+			if spec.opcode != 0xFF {
+				// Gives relative jumps an instruction to land on.
+				opcodes.extend(opcode_spec(0xFF).map(|it| it.sample(opcode.size())));
+			}
+			let mut script = Script { opcodes, trailer: vec![] };
+			script.label_jumps();
+
+			let text = disassemble(&script).unwrap();
+			let reassembled = assemble(&text).unwrap_or_else(|err| panic!("0x{:02X} {}: {err}\n{text}", spec.opcode, spec.mnemonic));
+			assert_eq!(
+				reassembled.binary_serialise().unwrap(),
+				script.binary_serialise().unwrap(),
+				"0x{:02X} {}:\n{text}",
+				spec.opcode,
+				spec.mnemonic
+			);
+		}
+	}
+}
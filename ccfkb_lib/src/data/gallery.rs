@@ -0,0 +1,298 @@
+//! Browsable overviews of every image in an archive, either as a static HTML gallery or as contact sheets.
+
+use crate::data::wipf::{blit, write_png, ArchiveWipf};
+use camino::Utf8Path;
+
+pub const THUMB_WIDTH: u32 = 160;
+pub const THUMB_HEIGHT: u32 = 120;
+
+const SHEET_COLUMNS: u32 = 6;
+const SHEET_ROWS: u32 = 5;
+const CELL_PADDING: u32 = 8;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 3;
+const CELL_WIDTH: u32 = THUMB_WIDTH + CELL_PADDING;
+const CELL_HEIGHT: u32 = THUMB_HEIGHT + CELL_PADDING + 2 * LINE_HEIGHT;
+
+const BACKGROUND: [u8; 4] = [0x20, 0x20, 0x20, 0xFF];
+const TEXT_COLOUR: [u8; 4] = [0xE0, 0xE0, 0xE0, 0xFF];
+
+/// One entry of a WIPF file, shrunk down for an overview.
+pub struct Thumbnail {
+	pub filename: String,
+	pub entry_no: usize,
+	pub source_width: u32,
+	pub source_height: u32,
+	pub x_offset: u32,
+	pub y_offset: u32,
+	pub width: u32,
+	pub height: u32,
+	pub rgba: Vec<u8>,
+}
+
+impl Thumbnail {
+	pub fn title(&self) -> String {
+		format!("{} {:03}", self.filename, self.entry_no)
+	}
+
+	pub fn caption(&self) -> String {
+		format!("{}x{} +{},{}", self.source_width, self.source_height, self.x_offset, self.y_offset)
+	}
+}
+
+/// Shrinks `rgba` to fit in `max_width` x `max_height`, keeping its aspect ratio. Every output pixel is the average
+/// of the source pixels it covers, weighted by alpha. Images that already fit are returned as they are.
+pub fn shrink_to_fit(width: u32, height: u32, rgba: &[u8], max_width: u32, max_height: u32) -> (u32, u32, Vec<u8>) {
+	if width <= max_width && height <= max_height {
+		return (width, height, rgba.to_vec());
+	}
+
+	let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+	let out_width = ((width as f64 * scale).round() as u32).max(1);
+	let out_height = ((height as f64 * scale).round() as u32).max(1);
+
+	let mut out = Vec::with_capacity((out_width * out_height) as usize * 4);
+	for out_y in 0..out_height {
+		let (y_start, y_end) = source_span(out_y, out_height, height);
+		for out_x in 0..out_width {
+			let (x_start, x_end) = source_span(out_x, out_width, width);
+
+			let mut sums = [0u64; 4];
+			for y in y_start..y_end {
+				for x in x_start..x_end {
+					let px = &rgba[((y * width + x) * 4) as usize..][..4];
+					let alpha = px[3] as u64;
+					for channel in 0..3 {
+						sums[channel] += px[channel] as u64 * alpha;
+					}
+					sums[3] += alpha;
+				}
+			}
+
+			let count = ((y_end - y_start) * (x_end - x_start)) as u64;
+			match sums[3] {
+				0 => out.extend([0, 0, 0, 0]),
+				alpha => out.extend([(sums[0] / alpha) as u8, (sums[1] / alpha) as u8, (sums[2] / alpha) as u8, (alpha / count) as u8]),
+			}
+		}
+	}
+
+	(out_width, out_height, out)
+}
+
+/// The source pixels `[start, end)` covered by output pixel `index`, always at least one wide.
+fn source_span(index: u32, out_len: u32, source_len: u32) -> (u32, u32) {
+	let start = (index as u64 * source_len as u64 / out_len as u64) as u32;
+	let end = ((index as u64 + 1) * source_len as u64 / out_len as u64) as u32;
+	(start, end.max(start + 1).min(source_len))
+}
+
+/// Makes a thumbnail of every entry of every WIPF file, in archive order.
+pub fn make_thumbnails(wipfs: &[ArchiveWipf]) -> Vec<Thumbnail> {
+	wipfs
+		.iter()
+		.flat_map(|wipf| {
+			wipf.images.iter().zip(wipf.rgba()).enumerate().map(|(entry_no, (image, rgba))| {
+				let (width, height, rgba) = shrink_to_fit(image.width, image.height, &rgba, THUMB_WIDTH, THUMB_HEIGHT);
+				Thumbnail {
+					filename: wipf.filename.to_string(),
+					entry_no,
+					source_width: image.width,
+					source_height: image.height,
+					x_offset: image.x_offset,
+					y_offset: image.y_offset,
+					width,
+					height,
+					rgba,
+				}
+			})
+		})
+		.collect()
+}
+
+/// The file a thumbnail is written to inside the gallery folder.
+fn thumbnail_name(thumbnail: &Thumbnail) -> String {
+	format!("thumbs/{}_{:03}.png", thumbnail.filename, thumbnail.entry_no)
+}
+
+fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Writes `index.html` and the thumbnails it shows into `out_folder`. Every thumbnail links to the full size entry.
+pub fn write_html_gallery(archive_name: &str, wipfs: &[ArchiveWipf], thumbnails: &[Thumbnail], out_folder: &Utf8Path) {
+	std::fs::create_dir_all(out_folder.join("thumbs")).unwrap();
+	std::fs::create_dir_all(out_folder.join("full")).unwrap();
+
+	for wipf in wipfs {
+		for (entry_no, (image, rgba)) in wipf.images.iter().zip(wipf.rgba()).enumerate() {
+			let out_path = out_folder.join("full").join(format!("{}_{entry_no:03}.png", wipf.filename));
+			write_png(&out_path, image.width, image.height, &rgba);
+		}
+	}
+
+	let mut figures = String::new();
+	for thumbnail in thumbnails {
+		write_png(&out_folder.join(thumbnail_name(thumbnail)), thumbnail.width, thumbnail.height, &thumbnail.rgba);
+
+		let filename = escape_html(&thumbnail.filename);
+		figures += &format!(
+			concat!(
+				"<figure data-name=\"{filename}\">",
+				"<a href=\"full/{filename}_{entry_no:03}.png\"><img src=\"{thumb}\" width=\"{width}\" height=\"{height}\" loading=\"lazy\"></a>",
+				"<figcaption>{title}<br>{caption}</figcaption></figure>\n"
+			),
+			filename = filename,
+			entry_no = thumbnail.entry_no,
+			thumb = escape_html(&thumbnail_name(thumbnail)),
+			width = thumbnail.width,
+			height = thumbnail.height,
+			title = escape_html(&thumbnail.title()),
+			caption = thumbnail.caption(),
+		);
+	}
+
+	let archive_name = escape_html(archive_name);
+	let html = format!(
+		r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{archive_name}</title>
+<style>
+body {{ background: #202020; color: #e0e0e0; font-family: monospace; }}
+figure {{ display: inline-block; width: {THUMB_WIDTH}px; margin: 4px; vertical-align: top; }}
+figure img {{ background: repeating-conic-gradient(#606060 0 25%, #404040 0 50%) 0 0 / 16px 16px; }}
+figcaption {{ font-size: 11px; overflow-wrap: anywhere; }}
+</style>
+</head>
+<body>
+<h1>{archive_name}</h1>
+<input id="filter" placeholder="Filter by file name" autofocus>
+<div id="gallery">
+{figures}</div>
+<script>
+document.getElementById("filter").addEventListener("input", event => {{
+	const filter = event.target.value.toUpperCase();
+	for (const figure of document.querySelectorAll("figure")) {{
+		figure.hidden = !figure.dataset.name.toUpperCase().includes(filter);
+	}}
+}});
+</script>
+</body>
+</html>
+"#
+	);
+
+	std::fs::write(out_folder.join("index.html"), html).unwrap();
+}
+
+/// Lays the thumbnails out on as many `contact_sheet_NNN.png` pages as needed, each labelled with its entry name,
+/// dimensions and offsets.
+pub fn write_contact_sheets(thumbnails: &[Thumbnail], out_folder: &Utf8Path) {
+	std::fs::create_dir_all(out_folder).unwrap();
+
+	let sheet_width = SHEET_COLUMNS * CELL_WIDTH + CELL_PADDING;
+	for (sheet_no, page) in thumbnails.chunks((SHEET_COLUMNS * SHEET_ROWS) as usize).enumerate() {
+		let rows = (page.len() as u32).div_ceil(SHEET_COLUMNS);
+		let sheet_height = rows * CELL_HEIGHT + CELL_PADDING;
+		let mut sheet = BACKGROUND.repeat((sheet_width * sheet_height) as usize);
+
+		for (idx, thumbnail) in page.iter().enumerate() {
+			let cell_x = CELL_PADDING + (idx as u32 % SHEET_COLUMNS) * CELL_WIDTH;
+			let cell_y = CELL_PADDING + (idx as u32 / SHEET_COLUMNS) * CELL_HEIGHT;
+
+			let thumb_x = cell_x + (THUMB_WIDTH - thumbnail.width) / 2;
+			let thumb_y = cell_y + (THUMB_HEIGHT - thumbnail.height) / 2;
+			draw_checkerboard(&mut sheet, sheet_width, thumb_x, thumb_y, thumbnail.width, thumbnail.height);
+			blit(&mut sheet, sheet_width, &thumbnail.rgba, thumbnail.width, thumb_x, thumb_y);
+
+			let text_y = cell_y + THUMB_HEIGHT + 2;
+			draw_text(&mut sheet, sheet_width, cell_x, text_y, THUMB_WIDTH, &thumbnail.title());
+			draw_text(&mut sheet, sheet_width, cell_x, text_y + LINE_HEIGHT, THUMB_WIDTH, &thumbnail.caption());
+		}
+
+		let out_path = out_folder.join(format!("contact_sheet_{sheet_no:03}.png"));
+		log::info!("Writing {out_path}");
+		write_png(&out_path, sheet_width, sheet_height, &sheet);
+	}
+}
+
+/// Fills a rectangle with a grey checkerboard so transparent parts of a thumbnail stand out.
+fn draw_checkerboard(canvas: &mut [u8], canvas_width: u32, x: u32, y: u32, width: u32, height: u32) {
+	for row in 0..height {
+		for column in 0..width {
+			let shade = if (row / 8 + column / 8) % 2 == 0 { 0x60 } else { 0x40 };
+			let start = (((y + row) * canvas_width + x + column) * 4) as usize;
+			canvas[start..start + 4].copy_from_slice(&[shade, shade, shade, 0xFF]);
+		}
+	}
+}
+
+/// Draws `text` with the built in 5x7 font, cutting it off at `max_width` pixels.
+fn draw_text(canvas: &mut [u8], canvas_width: u32, x: u32, y: u32, max_width: u32, text: &str) {
+	let max_chars = (max_width / (GLYPH_WIDTH + 1)) as usize;
+	for (idx, ch) in text.chars().take(max_chars).enumerate() {
+		let glyph_x = x + idx as u32 * (GLYPH_WIDTH + 1);
+		for (column, bits) in glyph(ch).iter().enumerate() {
+			for row in 0..GLYPH_HEIGHT {
+				if bits >> row & 1 == 1 {
+					let start = (((y + row) * canvas_width + glyph_x + column as u32) * 4) as usize;
+					canvas[start..start + 4].copy_from_slice(&TEXT_COLOUR);
+				}
+			}
+		}
+	}
+}
+
+/// The columns of a 5x7 glyph, lowest bit at the top. Lower case letters other than `x` are drawn as upper case,
+/// and anything the font does not cover is drawn as `?`.
+fn glyph(ch: char) -> [u8; 5] {
+	match ch.to_ascii_uppercase() {
+		' ' => [0x00, 0x00, 0x00, 0x00, 0x00],
+		'+' => [0x08, 0x08, 0x3E, 0x08, 0x08],
+		',' => [0x00, 0x50, 0x30, 0x00, 0x00],
+		'-' => [0x08, 0x08, 0x08, 0x08, 0x08],
+		'.' => [0x00, 0x60, 0x60, 0x00, 0x00],
+		'_' => [0x40, 0x40, 0x40, 0x40, 0x40],
+		'0' => [0x3E, 0x51, 0x49, 0x45, 0x3E],
+		'1' => [0x00, 0x42, 0x7F, 0x40, 0x00],
+		'2' => [0x42, 0x61, 0x51, 0x49, 0x46],
+		'3' => [0x21, 0x41, 0x45, 0x4B, 0x31],
+		'4' => [0x18, 0x14, 0x12, 0x7F, 0x10],
+		'5' => [0x27, 0x45, 0x45, 0x45, 0x39],
+		'6' => [0x3C, 0x4A, 0x49, 0x49, 0x30],
+		'7' => [0x01, 0x71, 0x09, 0x05, 0x03],
+		'8' => [0x36, 0x49, 0x49, 0x49, 0x36],
+		'9' => [0x06, 0x49, 0x49, 0x29, 0x1E],
+		'X' if ch == 'x' => [0x44, 0x28, 0x10, 0x28, 0x44],
+		'A' => [0x7E, 0x11, 0x11, 0x11, 0x7E],
+		'B' => [0x7F, 0x49, 0x49, 0x49, 0x36],
+		'C' => [0x3E, 0x41, 0x41, 0x41, 0x22],
+		'D' => [0x7F, 0x41, 0x41, 0x22, 0x1C],
+		'E' => [0x7F, 0x49, 0x49, 0x49, 0x41],
+		'F' => [0x7F, 0x09, 0x09, 0x09, 0x01],
+		'G' => [0x3E, 0x41, 0x49, 0x49, 0x7A],
+		'H' => [0x7F, 0x08, 0x08, 0x08, 0x7F],
+		'I' => [0x00, 0x41, 0x7F, 0x41, 0x00],
+		'J' => [0x20, 0x40, 0x41, 0x3F, 0x01],
+		'K' => [0x7F, 0x08, 0x14, 0x22, 0x41],
+		'L' => [0x7F, 0x40, 0x40, 0x40, 0x40],
+		'M' => [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+		'N' => [0x7F, 0x04, 0x08, 0x10, 0x7F],
+		'O' => [0x3E, 0x41, 0x41, 0x41, 0x3E],
+		'P' => [0x7F, 0x09, 0x09, 0x09, 0x06],
+		'Q' => [0x3E, 0x41, 0x51, 0x21, 0x5E],
+		'R' => [0x7F, 0x09, 0x19, 0x29, 0x46],
+		'S' => [0x46, 0x49, 0x49, 0x49, 0x31],
+		'T' => [0x01, 0x01, 0x7F, 0x01, 0x01],
+		'U' => [0x3F, 0x40, 0x40, 0x40, 0x3F],
+		'V' => [0x1F, 0x20, 0x40, 0x20, 0x1F],
+		'W' => [0x3F, 0x40, 0x38, 0x40, 0x3F],
+		'X' => [0x63, 0x14, 0x08, 0x14, 0x63],
+		'Y' => [0x07, 0x08, 0x70, 0x08, 0x07],
+		'Z' => [0x61, 0x51, 0x49, 0x45, 0x43],
+		_ => [0x02, 0x01, 0x51, 0x09, 0x06],
+	}
+}
@@ -0,0 +1,154 @@
+//! Comparison of two WIPF files with the same entry layout, such as an original asset and its re-imported edit.
+
+use crate::data::wipf::WipfImage;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageDiffError {
+	EntryCount { original: usize, patched: usize },
+	/// An entry whose size differs, so its pixels cannot be lined up.
+	Dimensions { entry: usize, original: (u32, u32), patched: (u32, u32) },
+}
+
+impl std::fmt::Display for ImageDiffError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ImageDiffError::EntryCount { original, patched } => {
+				write!(f, "the original has {original} entries but the patched file has {patched}")
+			}
+			ImageDiffError::Dimensions { entry, original, patched } => write!(
+				f,
+				"entry {entry} is {}x{} in the original but {}x{} in the patched file",
+				original.0, original.1, patched.0, patched.1
+			),
+		}
+	}
+}
+
+impl std::error::Error for ImageDiffError {}
+
+/// The smallest rectangle holding every changed pixel of an entry, `right` and `bottom` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+	pub left: u32,
+	pub top: u32,
+	pub right: u32,
+	pub bottom: u32,
+}
+
+/// How one entry differs between the two files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryDiff {
+	pub entry: usize,
+	pub width: u32,
+	pub height: u32,
+	/// The offsets of the entry, if they changed.
+	pub moved: Option<((u32, u32), (u32, u32))>,
+	/// The depth of the entry, if it changed.
+	pub depth: Option<(u16, u16)>,
+	/// Pixels whose colour or alpha differs, after resolving palettes.
+	pub changed_pixels: usize,
+	pub bounds: Option<Bounds>,
+	/// The palette indices whose colour differs, for 8 bit entries.
+	pub changed_colours: Vec<usize>,
+	/// One flag per pixel, set if it changed.
+	pub mask: Vec<bool>,
+}
+
+impl EntryDiff {
+	pub fn is_unchanged(&self) -> bool {
+		self.moved.is_none() && self.depth.is_none() && self.changed_pixels == 0 && self.changed_colours.is_empty()
+	}
+}
+
+/// Compares every entry of two decoded WIPF files. Pixels are compared as RGBA, so an 8 bit entry whose indices were
+/// remapped along with its palette counts as unchanged, and entries of different depths, such as an original and its
+/// re-import, can be compared. Palettes are only compared when both entries are 8 bit.
+pub fn diff_wipf(original: &[WipfImage], patched: &[WipfImage]) -> Result<Vec<EntryDiff>, ImageDiffError> {
+	if original.len() != patched.len() {
+		return Err(ImageDiffError::EntryCount { original: original.len(), patched: patched.len() });
+	}
+
+	let mut diffs = vec![];
+	for (entry, (a, b)) in original.iter().zip(patched).enumerate() {
+		if (a.width, a.height) != (b.width, b.height) {
+			return Err(ImageDiffError::Dimensions { entry, original: (a.width, a.height), patched: (b.width, b.height) });
+		}
+
+		let mask: Vec<bool> = a.to_rgba().chunks(4).zip(b.to_rgba().chunks(4)).map(|(a, b)| a != b).collect();
+
+		let mut bounds: Option<Bounds> = None;
+		for (idx, _) in mask.iter().enumerate().filter(|(_, changed)| **changed) {
+			let (x, y) = (idx as u32 % a.width, idx as u32 / a.width);
+			let it = bounds.get_or_insert(Bounds { left: x, top: y, right: x + 1, bottom: y + 1 });
+			it.left = it.left.min(x);
+			it.top = it.top.min(y);
+			it.right = it.right.max(x + 1);
+			it.bottom = it.bottom.max(y + 1);
+		}
+
+		let changed_colours = if a.depth == 8 && b.depth == 8 {
+			a.palette
+				.chunks(4)
+				.zip(b.palette.chunks(4))
+				.enumerate()
+				.filter(|(_, (a, b))| a[..3] != b[..3])
+				.map(|(idx, _)| idx)
+				.collect()
+		} else {
+			vec![]
+		};
+
+		let moved = ((a.x_offset, a.y_offset) != (b.x_offset, b.y_offset))
+			.then_some(((a.x_offset, a.y_offset), (b.x_offset, b.y_offset)));
+		let depth = (a.depth != b.depth).then_some((a.depth, b.depth));
+
+		diffs.push(EntryDiff {
+			entry,
+			width: a.width,
+			height: a.height,
+			moved,
+			depth,
+			changed_pixels: mask.iter().filter(|it| **it).count(),
+			bounds,
+			changed_colours,
+			mask,
+		});
+	}
+
+	Ok(diffs)
+}
+
+/// Draws the patched entry dimmed to grey, with the changed pixels in magenta and their bounding box in yellow.
+pub fn highlight_diff(diff: &EntryDiff, patched: &WipfImage) -> Vec<u8> {
+	let mut out: Vec<u8> = patched
+		.to_rgba()
+		.chunks(4)
+		.zip(&diff.mask)
+		.flat_map(|(px, changed)| {
+			if *changed {
+				return [0xFF, 0x00, 0xFF, 0xFF];
+			}
+			let grey = ((px[0] as u32 * 3 + px[1] as u32 * 6 + px[2] as u32) / 10 / 3) as u8;
+			[grey, grey, grey, 0xFF]
+		})
+		.collect();
+
+	if let Some(bounds) = diff.bounds {
+		let mut set = |x: u32, y: u32| {
+			let start = ((y * diff.width + x) * 4) as usize;
+			if out[start..start + 4] != [0xFF, 0x00, 0xFF, 0xFF] {
+				out[start..start + 4].copy_from_slice(&[0xFF, 0xFF, 0x00, 0xFF]);
+			}
+		};
+		for x in bounds.left..bounds.right {
+			set(x, bounds.top);
+			set(x, bounds.bottom - 1);
+		}
+		for y in bounds.top..bounds.bottom {
+			set(bounds.left, y);
+			set(bounds.right - 1, y);
+		}
+	}
+
+	out
+}
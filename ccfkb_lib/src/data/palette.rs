@@ -0,0 +1,137 @@
+/// The number of colours in the palette of an 8 bit WIPF entry.
+pub const PALETTE_COLOURS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteFormat {
+	/// GIMP palette, `.gpl`.
+	Gpl,
+	/// Paint Shop Pro palette, `.pal`.
+	JascPal,
+}
+
+impl PaletteFormat {
+	pub fn extension(&self) -> &'static str {
+		match self {
+			PaletteFormat::Gpl => "gpl",
+			PaletteFormat::JascPal => "pal",
+		}
+	}
+}
+
+impl std::str::FromStr for PaletteFormat {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"gpl" | "gimp" => Ok(PaletteFormat::Gpl),
+			"pal" | "jasc" => Ok(PaletteFormat::JascPal),
+			_ => Err(format!("unknown palette format {s}, expected gpl or pal")),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteError {
+	UnknownFormat,
+	BadColour { line: usize, text: String },
+	/// A JASC palette whose header disagrees with the number of colours listed.
+	BadCount { declared: usize, found: usize },
+	WrongColourCount { expected: usize, found: usize },
+}
+
+impl std::fmt::Display for PaletteError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			PaletteError::UnknownFormat => write!(f, "not a GIMP or JASC palette"),
+			PaletteError::BadColour { line, text } => write!(f, "line {line} is not a colour: {text:?}"),
+			PaletteError::BadCount { declared, found } => {
+				write!(f, "palette header declares {declared} colours but {found} are listed")
+			}
+			PaletteError::WrongColourCount { expected, found } => {
+				write!(f, "palette has {found} colours, WIPF palettes need exactly {expected}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for PaletteError {}
+
+/// Reads the colours out of a BGRA WIPF palette.
+pub fn palette_from_bgra(bgra: &[u8]) -> Vec<[u8; 3]> {
+	bgra.chunks(4).map(|it| [it[2], it[1], it[0]]).collect()
+}
+
+/// Writes `colours` into a BGRA WIPF palette, leaving the fourth byte of every entry alone.
+pub fn apply_palette(bgra: &mut [u8], colours: &[[u8; 3]]) -> Result<(), PaletteError> {
+	if colours.len() != PALETTE_COLOURS {
+		return Err(PaletteError::WrongColourCount { expected: PALETTE_COLOURS, found: colours.len() });
+	}
+
+	for (entry, [r, g, b]) in bgra.chunks_mut(4).zip(colours) {
+		entry[0] = *b;
+		entry[1] = *g;
+		entry[2] = *r;
+	}
+
+	Ok(())
+}
+
+pub fn write_palette(name: &str, colours: &[[u8; 3]], format: PaletteFormat) -> String {
+	let mut out = String::new();
+	match format {
+		PaletteFormat::Gpl => {
+			out.push_str(&format!("GIMP Palette\nName: {name}\nColumns: 16\n#\n"));
+			for (idx, [r, g, b]) in colours.iter().enumerate() {
+				out.push_str(&format!("{r:3} {g:3} {b:3}\tIndex {idx}\n"));
+			}
+		}
+		PaletteFormat::JascPal => {
+			out.push_str(&format!("JASC-PAL\r\n0100\r\n{}\r\n", colours.len()));
+			for [r, g, b] in colours {
+				out.push_str(&format!("{r} {g} {b}\r\n"));
+			}
+		}
+	}
+	out
+}
+
+fn parse_colour(line_no: usize, line: &str) -> Result<[u8; 3], PaletteError> {
+	let bad_colour = || PaletteError::BadColour { line: line_no + 1, text: line.to_string() };
+
+	let mut components = line.split_whitespace().map(|it| it.parse::<u8>());
+	let mut next = || components.next().and_then(Result::ok).ok_or_else(bad_colour);
+	Ok([next()?, next()?, next()?])
+}
+
+/// Reads a GIMP or JASC palette, telling them apart by their first line.
+pub fn parse_palette(text: &str) -> Result<Vec<[u8; 3]>, PaletteError> {
+	let mut lines = text.lines().enumerate().map(|(idx, line)| (idx, line.trim()));
+
+	match lines.next() {
+		Some((_, "GIMP Palette")) => lines
+			.filter(|(_, line)| {
+				!line.is_empty() && !line.starts_with('#') && !line.starts_with("Name:") && !line.starts_with("Columns:")
+			})
+			.map(|(idx, line)| parse_colour(idx, line))
+			.collect(),
+		Some((_, "JASC-PAL")) => {
+			let _version = lines.next();
+			let (idx, declared) = lines.next().ok_or(PaletteError::UnknownFormat)?;
+			let declared = declared
+				.parse::<usize>()
+				.map_err(|_| PaletteError::BadColour { line: idx + 1, text: declared.to_string() })?;
+
+			let colours = lines
+				.filter(|(_, line)| !line.is_empty())
+				.map(|(idx, line)| parse_colour(idx, line))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			if colours.len() != declared {
+				return Err(PaletteError::BadCount { declared, found: colours.len() });
+			}
+
+			Ok(colours)
+		}
+		_ => Err(PaletteError::UnknownFormat),
+	}
+}
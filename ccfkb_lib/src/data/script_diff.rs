@@ -0,0 +1,623 @@
+//! Comparison of two versions of a script by instruction rather than by line of YAML, such as before and after a
+//! patch of the game, and a three-way merge of the translations and notes two people made to the same script.
+//!
+//! Instructions are lined up by opcode and field values, leaving out jump targets, translations and notes, so
+//! instructions that only moved are not reported. A jump counts as changed when it no longer goes to the instruction
+//! it was lined up with.
+
+use crate::opcodes::{opcode_spec, FieldKind, OpField, Opcode, Script, TLString};
+use serde_derive::Serialize;
+use similar::{capture_diff_slices, get_diff_ratio, Algorithm, DiffOp};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// What an instruction is lined up by.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum KeyField<'a> {
+	Byte(u8),
+	Word(u16),
+	DWord(u32),
+	Text(&'a str),
+	Choice(u16, &'a str, &'a [u8]),
+	Padding(u8),
+	Raw(&'a [u8]),
+	Jump,
+}
+
+fn is_jump(opcode: &Opcode, field: usize) -> bool {
+	matches!(opcode.fields.get(field), Some(OpField::Label(_)))
+		|| opcode_spec(opcode.opcode)
+			.and_then(|it| it.fields.get(field))
+			.is_some_and(|it| matches!(it.kind, FieldKind::Jump | FieldKind::RelativeJump))
+}
+
+fn key(opcode: &Opcode) -> (u8, Vec<KeyField<'_>>) {
+	let fields = opcode
+		.fields
+		.iter()
+		.enumerate()
+		.flat_map(|(idx, field)| match field {
+			_ if is_jump(opcode, idx) => vec![KeyField::Jump],
+			OpField::Byte(value) => vec![KeyField::Byte(*value)],
+			OpField::Word(value) => vec![KeyField::Word(*value)],
+			OpField::DWord(value) => vec![KeyField::DWord(*value)],
+			OpField::String(text) => vec![KeyField::Text(&text.raw)],
+			OpField::Choice(choices) => {
+				choices.iter().map(|it| KeyField::Choice(it.arg1, &it.choice_str.raw, &it.trailer)).collect()
+			}
+			OpField::Padding(size) => vec![KeyField::Padding(*size)],
+			OpField::Raw(bytes) => vec![KeyField::Raw(bytes)],
+			OpField::Label(_) => vec![KeyField::Jump],
+		})
+		.collect();
+	(opcode.opcode, fields)
+}
+
+/// How an instruction of the old script lines up with one of the new script, by index.
+#[derive(Debug, Clone, Copy)]
+enum Aligned {
+	/// The same key, so at most translations, notes and jump targets differ.
+	Same(usize, usize),
+	/// Taken to be an edit of the old instruction, see [`is_edit_of`].
+	Changed(usize, usize),
+	Removed(usize),
+	Inserted(usize),
+}
+
+fn align(old: &Script, new: &Script) -> Vec<Aligned> {
+	let old_keys: Vec<_> = old.opcodes.iter().map(key).collect();
+	let new_keys: Vec<_> = new.opcodes.iter().map(key).collect();
+
+	let mut aligned = vec![];
+	for op in capture_diff_slices(Algorithm::Myers, &old_keys, &new_keys) {
+		match op {
+			DiffOp::Equal { old_index, new_index, len } => {
+				aligned.extend((0..len).map(|it| Aligned::Same(old_index + it, new_index + it)));
+			}
+			DiffOp::Delete { old_index, old_len, .. } => aligned.extend((old_index..old_index + old_len).map(Aligned::Removed)),
+			DiffOp::Insert { new_index, new_len, .. } => aligned.extend((new_index..new_index + new_len).map(Aligned::Inserted)),
+			DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+				let old_run = &old.opcodes[old_index..old_index + old_len];
+				let new_run = &new.opcodes[new_index..new_index + new_len];
+				aligned.extend(pair_replaced(old_run, new_run).into_iter().map(|it| match it {
+					Aligned::Same(old, new) | Aligned::Changed(old, new) => Aligned::Changed(old_index + old, new_index + new),
+					Aligned::Removed(old) => Aligned::Removed(old_index + old),
+					Aligned::Inserted(new) => Aligned::Inserted(new_index + new),
+				}));
+			}
+		}
+	}
+	aligned
+}
+
+/// Runs longer than this, in old times new instructions, are paired by opcode alone.
+const MAX_PAIRED_RUN: usize = 1 << 16;
+
+/// What [`is_edit_of`] compares of an instruction: its opcode and the characters of its strings.
+fn edit_key(opcode: &Opcode) -> (u8, Vec<char>) {
+	let raw = texts(opcode).iter().map(|it| it.raw.as_str()).collect::<Vec<_>>().join("\n");
+	(opcode.opcode, raw.chars().collect())
+}
+
+/// Whether two instructions of a replaced run are taken to be edits of each other: the same opcode, and for text,
+/// strings that are at least half the same.
+fn is_edit_of((old_opcode, old_raw): &(u8, Vec<char>), (new_opcode, new_raw): &(u8, Vec<char>)) -> bool {
+	if old_opcode != new_opcode {
+		return false;
+	}
+	let len = old_raw.len() + new_raw.len();
+	// Strings this different in length cannot be half the same.
+	if 4 * old_raw.len().min(new_raw.len()) < len {
+		return false;
+	}
+	len == 0 || get_diff_ratio(&capture_diff_slices(Algorithm::Myers, old_raw, new_raw), old_raw.len(), new_raw.len()) >= 0.5
+}
+
+/// Lines up the instructions of a run that differs between the two scripts, by index into the run.
+fn pair_replaced(old: &[Opcode], new: &[Opcode]) -> Vec<Aligned> {
+	if old.len() * new.len() > MAX_PAIRED_RUN {
+		let old_opcodes: Vec<u8> = old.iter().map(|it| it.opcode).collect();
+		let new_opcodes: Vec<u8> = new.iter().map(|it| it.opcode).collect();
+		return capture_diff_slices(Algorithm::Myers, &old_opcodes, &new_opcodes)
+			.iter()
+			.flat_map(|op| match *op {
+				DiffOp::Equal { old_index, new_index, len } => (0..len).map(|it| Aligned::Changed(old_index + it, new_index + it)).collect(),
+				other => {
+					let (_, old, new) = other.as_tag_tuple();
+					old.map(Aligned::Removed).chain(new.map(Aligned::Inserted)).collect::<Vec<_>>()
+				}
+			})
+			.collect();
+	}
+
+	// Whether each old instruction is an edit of each new one, worked out once for both passes below.
+	let new_keys: Vec<_> = new.iter().map(edit_key).collect();
+	let edits: Vec<Vec<bool>> = old
+		.iter()
+		.map(|old| {
+			let old_key = edit_key(old);
+			new_keys.iter().map(|new_key| is_edit_of(&old_key, new_key)).collect()
+		})
+		.collect();
+
+	// The longest common subsequence of edits, from the end.
+	let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			lengths[i][j] = if edits[i][j] {
+				lengths[i + 1][j + 1] + 1
+			} else {
+				lengths[i + 1][j].max(lengths[i][j + 1])
+			};
+		}
+	}
+
+	let mut aligned = vec![];
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() && j < new.len() {
+		if edits[i][j] && lengths[i][j] == lengths[i + 1][j + 1] + 1 {
+			aligned.push(Aligned::Changed(i, j));
+			(i, j) = (i + 1, j + 1);
+		} else if lengths[i + 1][j] >= lengths[i][j + 1] {
+			aligned.push(Aligned::Removed(i));
+			i += 1;
+		} else {
+			aligned.push(Aligned::Inserted(j));
+			j += 1;
+		}
+	}
+	aligned.extend((i..old.len()).map(Aligned::Removed));
+	aligned.extend((j..new.len()).map(Aligned::Inserted));
+	aligned
+}
+
+/// A script with its instructions by label.
+struct Version<'a> {
+	script: &'a Script,
+	labels: HashMap<&'a str, usize>,
+}
+
+impl<'a> Version<'a> {
+	fn new(script: &'a Script) -> Self {
+		let labels = script.opcodes.iter().enumerate().filter_map(|(idx, it)| Some((it.label.as_deref()?, idx))).collect();
+		Version { script, labels }
+	}
+
+	/// The index and address of the instruction a jump field goes to. The index is `None` for targets no instruction
+	/// starts at.
+	fn jump_target(&self, opcode: &Opcode, field: usize) -> (Option<usize>, usize) {
+		match opcode.fields.get(field) {
+			Some(OpField::Label(label)) => {
+				let index = self.labels.get(label.as_str()).copied();
+				(index, index.map(|it| self.script.opcodes[it].address).unwrap_or_default())
+			}
+			Some(OpField::DWord(value)) => {
+				let relative = opcode_spec(opcode.opcode)
+					.and_then(|it| it.fields.get(field))
+					.is_some_and(|it| it.kind == FieldKind::RelativeJump);
+				let address = if relative { ((opcode.address + opcode.size()) as u32).wrapping_add(*value) } else { *value };
+				(None, address as usize)
+			}
+			_ => (None, 0),
+		}
+	}
+}
+
+/// A field that differs between two lined up instructions.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldChange {
+	/// A string, or the string of the option at `choice` of a 0x02.
+	Text { field: usize, choice: Option<usize>, old: TLString, new: TLString },
+	/// A jump that goes somewhere else, with the address it goes to in each script.
+	Jump { field: usize, old: usize, new: usize },
+	Value { field: usize, old: String, new: String },
+}
+
+fn field_value(field: &OpField) -> String {
+	match field {
+		OpField::Byte(value) => format!("0x{value:02X}"),
+		OpField::Word(value) => format!("0x{value:04X}"),
+		OpField::DWord(value) => format!("0x{value:08X}"),
+		OpField::String(text) => format!("{:?}", text.raw),
+		OpField::Choice(choices) => format!("{} options", choices.len()),
+		OpField::Padding(size) => format!("{size} bytes of padding"),
+		OpField::Raw(bytes) => format!("{bytes:02X?}"),
+		OpField::Label(label) => label.clone(),
+	}
+}
+
+fn text_differs(old: &TLString, new: &TLString) -> bool {
+	old.raw != new.raw || old.translation != new.translation || old.notes != new.notes
+}
+
+/// Compares the fields of two lined up instructions. `index` maps the old index of an instruction to the new one.
+fn field_changes(old_version: &Version, old: &Opcode, new_version: &Version, new: &Opcode, index: &HashMap<usize, usize>) -> Vec<FieldChange> {
+	let mut changes = vec![];
+	for field in 0..old.fields.len().max(new.fields.len()) {
+		let (Some(old_field), Some(new_field)) = (old.fields.get(field), new.fields.get(field)) else {
+			let value = |it: Option<&OpField>| it.map_or("(none)".to_string(), field_value);
+			changes.push(FieldChange::Value { field, old: value(old.fields.get(field)), new: value(new.fields.get(field)) });
+			continue;
+		};
+
+		if is_jump(old, field) && is_jump(new, field) {
+			let (old_index, old_address) = old_version.jump_target(old, field);
+			let (new_index, new_address) = new_version.jump_target(new, field);
+			let same = match (old_index, new_index) {
+				(Some(old_index), Some(new_index)) => index.get(&old_index) == Some(&new_index),
+				(None, None) => old_address == new_address,
+				_ => false,
+			};
+			if !same {
+				changes.push(FieldChange::Jump { field, old: old_address, new: new_address });
+			}
+			continue;
+		}
+
+		match (old_field, new_field) {
+			(OpField::String(old_text), OpField::String(new_text)) => {
+				if text_differs(old_text, new_text) {
+					changes.push(FieldChange::Text { field, choice: None, old: old_text.clone(), new: new_text.clone() });
+				}
+			}
+			(OpField::Choice(old_choices), OpField::Choice(new_choices)) => {
+				if old_choices.len() != new_choices.len() {
+					changes.push(FieldChange::Value { field, old: field_value(old_field), new: field_value(new_field) });
+				}
+				for (choice, (old_choice, new_choice)) in old_choices.iter().zip(new_choices).enumerate() {
+					if old_choice.arg1 != new_choice.arg1 || old_choice.trailer != new_choice.trailer {
+						changes.push(FieldChange::Value {
+							field,
+							old: format!("option {} 0x{:04X} {:02X?}", choice + 1, old_choice.arg1, old_choice.trailer),
+							new: format!("option {} 0x{:04X} {:02X?}", choice + 1, new_choice.arg1, new_choice.trailer),
+						});
+					}
+					if text_differs(&old_choice.choice_str, &new_choice.choice_str) {
+						changes.push(FieldChange::Text {
+							field,
+							choice: Some(choice),
+							old: old_choice.choice_str.clone(),
+							new: new_choice.choice_str.clone(),
+						});
+					}
+				}
+			}
+			_ => {
+				let (old_value, new_value) = (field_value(old_field), field_value(new_field));
+				if old_value != new_value {
+					changes.push(FieldChange::Value { field, old: old_value, new: new_value });
+				}
+			}
+		}
+	}
+	changes
+}
+
+fn texts(opcode: &Opcode) -> Vec<TLString> {
+	opcode
+		.fields
+		.iter()
+		.flat_map(|field| match field {
+			OpField::String(text) => vec![text.clone()],
+			OpField::Choice(choices) => choices.iter().map(|it| it.choice_str.clone()).collect(),
+			_ => vec![],
+		})
+		.collect()
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum InstructionChange {
+	/// An instruction only the new script has, with its strings.
+	Inserted { address: usize, opcode: u8, mnemonic: Option<&'static str>, texts: Vec<TLString> },
+	/// An instruction only the old script has, with its strings.
+	Removed { address: usize, opcode: u8, mnemonic: Option<&'static str>, texts: Vec<TLString> },
+	Changed { old_address: usize, new_address: usize, opcode: u8, mnemonic: Option<&'static str>, fields: Vec<FieldChange> },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScriptDiff {
+	/// In the order of the new script, with removed instructions where they were.
+	pub changes: Vec<InstructionChange>,
+	pub unchanged: usize,
+	pub trailer_changed: bool,
+}
+
+/// Labels the jumps of a copy of `script`, so both versions refer to their targets the same way.
+fn labelled(script: &Script) -> Script {
+	let mut script = script.clone();
+	script.label_jumps();
+	script
+}
+
+fn mnemonic(opcode: u8) -> Option<&'static str> {
+	opcode_spec(opcode).map(|it| it.mnemonic)
+}
+
+fn quoted(text: &Option<String>) -> String {
+	text.as_ref().map_or("(none)".to_string(), |it| format!("{it:?}"))
+}
+
+impl ScriptDiff {
+	pub fn new(old: &Script, new: &Script) -> Self {
+		let (old, new) = (labelled(old), labelled(new));
+		let aligned = align(&old, &new);
+		let (old_version, new_version) = (Version::new(&old), Version::new(&new));
+		let index: HashMap<usize, usize> = aligned
+			.iter()
+			.filter_map(|it| match it {
+				Aligned::Same(old, new) | Aligned::Changed(old, new) => Some((*old, *new)),
+				_ => None,
+			})
+			.collect();
+
+		let mut changes = vec![];
+		let mut unchanged = 0;
+		for it in aligned {
+			match it {
+				Aligned::Same(old_idx, new_idx) | Aligned::Changed(old_idx, new_idx) => {
+					let (old_opcode, new_opcode) = (&old.opcodes[old_idx], &new.opcodes[new_idx]);
+					let fields = field_changes(&old_version, old_opcode, &new_version, new_opcode, &index);
+					if fields.is_empty() {
+						unchanged += 1;
+						continue;
+					}
+					changes.push(InstructionChange::Changed {
+						old_address: old_opcode.address,
+						new_address: new_opcode.address,
+						opcode: new_opcode.opcode,
+						mnemonic: mnemonic(new_opcode.opcode),
+						fields,
+					});
+				}
+				Aligned::Removed(idx) => {
+					let opcode = &old.opcodes[idx];
+					changes.push(InstructionChange::Removed {
+						address: opcode.address,
+						opcode: opcode.opcode,
+						mnemonic: mnemonic(opcode.opcode),
+						texts: texts(opcode),
+					});
+				}
+				Aligned::Inserted(idx) => {
+					let opcode = &new.opcodes[idx];
+					changes.push(InstructionChange::Inserted {
+						address: opcode.address,
+						opcode: opcode.opcode,
+						mnemonic: mnemonic(opcode.opcode),
+						texts: texts(opcode),
+					});
+				}
+			}
+		}
+
+		ScriptDiff { changes, unchanged, trailer_changed: old.trailer != new.trailer }
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty() && !self.trailer_changed
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// One line per instruction, marked `+`, `-` or `~`, with the changed fields below it. Text shows its raw string
+	/// and its translation separately, and a translation whose raw string changed under it is shown as well.
+	pub fn to_text(&self) -> String {
+		let mut out = String::new();
+		let name = |opcode: &u8, mnemonic: &Option<&str>| format!("0x{opcode:02X} {}", mnemonic.unwrap_or("(unknown)"));
+		for change in &self.changes {
+			match change {
+				InstructionChange::Inserted { address, opcode, mnemonic, texts } => {
+					writeln!(out, "+ 0x{address:08X} {}", name(opcode, mnemonic)).unwrap();
+					write_texts(&mut out, texts);
+				}
+				InstructionChange::Removed { address, opcode, mnemonic, texts } => {
+					writeln!(out, "- 0x{address:08X} {}", name(opcode, mnemonic)).unwrap();
+					write_texts(&mut out, texts);
+				}
+				InstructionChange::Changed { old_address, new_address, opcode, mnemonic, fields } => {
+					writeln!(out, "~ 0x{old_address:08X} -> 0x{new_address:08X} {}", name(opcode, mnemonic)).unwrap();
+					for field in fields {
+						write_field_change(&mut out, field);
+					}
+				}
+			}
+		}
+		if self.trailer_changed {
+			writeln!(out, "~ trailer").unwrap();
+		}
+		writeln!(out, "{} changed, {} unchanged", self.changes.len(), self.unchanged).unwrap();
+		out
+	}
+}
+
+fn write_texts(out: &mut String, texts: &[TLString]) {
+	for text in texts {
+		writeln!(out, "      raw:         {:?}", text.raw).unwrap();
+		if text.translation.is_some() {
+			writeln!(out, "      translation: {}", quoted(&text.translation)).unwrap();
+		}
+	}
+}
+
+fn write_field_change(out: &mut String, change: &FieldChange) {
+	match change {
+		FieldChange::Text { field, choice, old, new } => {
+			let place = match choice {
+				Some(choice) => format!("field {field} option {}", choice + 1),
+				None => format!("field {field}"),
+			};
+			writeln!(out, "    {place}").unwrap();
+			if old.raw != new.raw {
+				writeln!(out, "      raw:         {:?} -> {:?}", old.raw, new.raw).unwrap();
+			}
+			if old.translation != new.translation {
+				writeln!(out, "      translation: {} -> {}", quoted(&old.translation), quoted(&new.translation)).unwrap();
+			} else if old.raw != new.raw && new.translation.is_some() {
+				writeln!(out, "      translation: {} (unchanged)", quoted(&new.translation)).unwrap();
+			}
+			if old.notes != new.notes {
+				writeln!(out, "      notes:       {} -> {}", quoted(&old.notes), quoted(&new.notes)).unwrap();
+			}
+		}
+		FieldChange::Jump { field, old, new } => writeln!(out, "    field {field} jump: 0x{old:08X} -> 0x{new:08X}").unwrap(),
+		FieldChange::Value { field, old, new } => writeln!(out, "    field {field}: {old} -> {new}").unwrap(),
+	}
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextPart {
+	Translation,
+	Notes,
+}
+
+impl std::fmt::Display for TextPart {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			TextPart::Translation => write!(f, "translation"),
+			TextPart::Notes => write!(f, "notes"),
+		}
+	}
+}
+
+/// A translation or notes both sides changed differently. The merged script keeps ours.
+#[derive(Serialize, Debug, Clone)]
+pub struct MergeConflict {
+	/// The address of the instruction in our script.
+	pub address: usize,
+	pub field: usize,
+	pub choice: Option<usize>,
+	pub raw: String,
+	pub part: TextPart,
+	pub base: Option<String>,
+	pub ours: Option<String>,
+	pub theirs: Option<String>,
+}
+
+impl std::fmt::Display for MergeConflict {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "0x{:08X} field {}", self.address, self.field)?;
+		if let Some(choice) = self.choice {
+			write!(f, " option {}", choice + 1)?;
+		}
+		write!(
+			f,
+			" {:?}: {} changed to {} by us and to {} by them",
+			self.raw,
+			self.part,
+			quoted(&self.ours),
+			quoted(&self.theirs)
+		)
+	}
+}
+
+pub struct TranslationMerge {
+	pub script: Script,
+	/// How many translations and notes were taken from their script.
+	pub merged: usize,
+	pub conflicts: Vec<MergeConflict>,
+}
+
+/// Where the strings of an instruction are: the field, and the option for 0x02.
+fn text_slots(opcode: &Opcode) -> Vec<(usize, Option<usize>)> {
+	opcode
+		.fields
+		.iter()
+		.enumerate()
+		.flat_map(|(field, it)| match it {
+			OpField::String(_) => vec![(field, None)],
+			OpField::Choice(choices) => (0..choices.len()).map(|choice| (field, Some(choice))).collect(),
+			_ => vec![],
+		})
+		.collect()
+}
+
+fn text_at(opcode: &Opcode, (field, choice): (usize, Option<usize>)) -> Option<&TLString> {
+	match (opcode.fields.get(field)?, choice) {
+		(OpField::String(text), None) => Some(text),
+		(OpField::Choice(choices), Some(choice)) => choices.get(choice).map(|it| &it.choice_str),
+		_ => None,
+	}
+}
+
+fn text_at_mut(opcode: &mut Opcode, (field, choice): (usize, Option<usize>)) -> Option<&mut TLString> {
+	match (opcode.fields.get_mut(field)?, choice) {
+		(OpField::String(text), None) => Some(text),
+		(OpField::Choice(choices), Some(choice)) => choices.get_mut(choice).map(|it| &mut it.choice_str),
+		_ => None,
+	}
+}
+
+/// The instructions of `to` lined up with the same instruction of `from`, by index.
+fn same_instructions(from: &Script, to: &Script) -> HashMap<usize, usize> {
+	align(from, to)
+		.into_iter()
+		.filter_map(|it| match it {
+			Aligned::Same(from, to) => Some((to, from)),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Merges the translation and notes changes `theirs` made to `base` into `ours`. Everything else, instructions
+/// included, comes from `ours`, and only strings whose raw text is the same in both are merged. A string that is
+/// new on both sides has no base, so any difference between them is a conflict.
+pub fn merge_translations(base: &Script, ours: &Script, theirs: &Script) -> TranslationMerge {
+	let ours_to_base = same_instructions(base, ours);
+	let ours_to_theirs = same_instructions(theirs, ours);
+
+	let mut script = ours.clone();
+	let mut merged = 0;
+	let mut conflicts = vec![];
+	for (idx, opcode) in script.opcodes.iter_mut().enumerate() {
+		let Some(&theirs_idx) = ours_to_theirs.get(&idx) else {
+			continue;
+		};
+		let base_opcode = ours_to_base.get(&idx).map(|it| &base.opcodes[*it]);
+
+		for slot in text_slots(opcode) {
+			let Some(their_text) = text_at(&theirs.opcodes[theirs_idx], slot) else {
+				continue;
+			};
+			let base_text = base_opcode.and_then(|it| text_at(it, slot));
+			let address = opcode.address;
+			let our_text = text_at_mut(opcode, slot).unwrap();
+
+			for part in [TextPart::Translation, TextPart::Notes] {
+				let get = |text: &TLString| match part {
+					TextPart::Translation => text.translation.clone(),
+					TextPart::Notes => text.notes.clone(),
+				};
+				// Without a base string, neither side can be told to be the one that changed.
+				let (ours, theirs, base) = (get(our_text), get(their_text), base_text.map(get));
+				if ours == theirs || base.as_ref() == Some(&theirs) {
+					continue;
+				}
+				if base.as_ref() == Some(&ours) {
+					match part {
+						TextPart::Translation => our_text.translation = theirs,
+						TextPart::Notes => our_text.notes = theirs,
+					}
+					merged += 1;
+					continue;
+				}
+				conflicts.push(MergeConflict {
+					address,
+					field: slot.0,
+					choice: slot.1,
+					raw: our_text.raw.clone(),
+					part,
+					base: base.flatten(),
+					ours,
+					theirs,
+				});
+			}
+		}
+	}
+
+	TranslationMerge { script, merged, conflicts }
+}
@@ -0,0 +1,233 @@
+//! Translation progress, counted over the lines of translation documents per script, per archive and overall.
+
+use crate::data::text_script::DocLine;
+use crate::opcodes::TLString;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// The speaker lines without one are counted under.
+pub const NO_SPEAKER: &str = "(no speaker)";
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SpeakerStats {
+	pub lines: usize,
+	pub translated_lines: usize,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TextStats {
+	/// Textboxes, leaving out scene titles and choices.
+	pub lines: usize,
+	/// The characters of the original text of lines, scene titles and choices, leaving out ASCII, which is only used
+	/// for control codes.
+	pub raw_chars: usize,
+	pub translated_lines: usize,
+	pub lines_with_notes: usize,
+	pub scene_titles: usize,
+	pub translated_scene_titles: usize,
+	pub choices: usize,
+	pub untranslated_choices: usize,
+	/// Lines per speaker, by original name.
+	pub speakers: BTreeMap<String, SpeakerStats>,
+}
+
+fn is_translated(text: &TLString) -> bool {
+	text.translation.as_deref().is_some_and(|it| !it.trim_end_matches("%K%P").trim().is_empty())
+}
+
+fn has_notes(text: &TLString) -> bool {
+	text.notes.as_deref().is_some_and(|it| !it.trim().is_empty())
+}
+
+fn raw_chars(text: &TLString) -> usize {
+	text.raw.chars().filter(|it| !it.is_ascii()).count()
+}
+
+impl TextStats {
+	pub fn from_doclines(doclines: &[DocLine]) -> Self {
+		let mut stats = TextStats::default();
+		for docline in doclines {
+			match docline {
+				DocLine::Line(line) => stats.add_line(NO_SPEAKER, &line.translation),
+				DocLine::SpeakerLine(line) => stats.add_line(&line.speaker_translation.raw, &line.translation),
+				DocLine::Scene(line) => {
+					stats.scene_titles += 1;
+					stats.translated_scene_titles += is_translated(&line.translation) as usize;
+					stats.raw_chars += raw_chars(&line.translation);
+				}
+				DocLine::Choices(line) => {
+					for choice in &line.choices {
+						stats.choices += 1;
+						stats.untranslated_choices += !is_translated(choice) as usize;
+						stats.raw_chars += raw_chars(choice);
+					}
+				}
+			}
+		}
+		stats
+	}
+
+	fn add_line(&mut self, speaker: &str, text: &TLString) {
+		let translated = is_translated(text);
+		self.lines += 1;
+		self.translated_lines += translated as usize;
+		self.lines_with_notes += has_notes(text) as usize;
+		self.raw_chars += raw_chars(text);
+
+		let speaker = self.speakers.entry(speaker.to_string()).or_default();
+		speaker.lines += 1;
+		speaker.translated_lines += translated as usize;
+	}
+
+	pub fn add(&mut self, other: &TextStats) {
+		self.lines += other.lines;
+		self.raw_chars += other.raw_chars;
+		self.translated_lines += other.translated_lines;
+		self.lines_with_notes += other.lines_with_notes;
+		self.scene_titles += other.scene_titles;
+		self.translated_scene_titles += other.translated_scene_titles;
+		self.choices += other.choices;
+		self.untranslated_choices += other.untranslated_choices;
+		for (name, it) in &other.speakers {
+			let speaker = self.speakers.entry(name.clone()).or_default();
+			speaker.lines += it.lines;
+			speaker.translated_lines += it.translated_lines;
+		}
+	}
+
+	/// The share of lines translated, in percent.
+	pub fn progress(&self) -> f64 {
+		percent(self.translated_lines, self.lines)
+	}
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+	if whole == 0 { 100.0 } else { part as f64 * 100.0 / whole as f64 }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScriptStats {
+	pub name: String,
+	#[serde(flatten)]
+	pub stats: TextStats,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ArchiveStats {
+	pub name: String,
+	pub scripts: Vec<ScriptStats>,
+	pub total: TextStats,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StatsReport {
+	pub archives: Vec<ArchiveStats>,
+	pub total: TextStats,
+}
+
+const TABLE_HEADER: [&str; 8] = ["", "Lines", "Translated", "%", "Notes", "Raw chars", "Choices", "Untranslated choices"];
+
+fn table_row(name: &str, stats: &TextStats) -> [String; 8] {
+	[
+		name.to_string(),
+		stats.lines.to_string(),
+		stats.translated_lines.to_string(),
+		format!("{:.1}", stats.progress()),
+		stats.lines_with_notes.to_string(),
+		stats.raw_chars.to_string(),
+		stats.choices.to_string(),
+		stats.untranslated_choices.to_string(),
+	]
+}
+
+impl StatsReport {
+	/// Groups the counts of every script, given as (archive, script, counts), by archive.
+	pub fn build(scripts: impl IntoIterator<Item = (String, String, TextStats)>) -> Self {
+		let mut archives: BTreeMap<String, Vec<ScriptStats>> = BTreeMap::new();
+		for (archive, name, stats) in scripts {
+			archives.entry(archive).or_default().push(ScriptStats { name, stats });
+		}
+
+		let mut total = TextStats::default();
+		let archives = archives
+			.into_iter()
+			.map(|(name, mut scripts)| {
+				scripts.sort_by(|a, b| a.name.cmp(&b.name));
+				let mut archive_total = TextStats::default();
+				scripts.iter().for_each(|it| archive_total.add(&it.stats));
+				total.add(&archive_total);
+				ArchiveStats { name, scripts, total: archive_total }
+			})
+			.collect();
+
+		StatsReport { archives, total }
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// One row per script with a total per archive and overall, then the lines of every speaker.
+	pub fn to_table(&self) -> String {
+		let mut rows = vec![TABLE_HEADER.map(str::to_string)];
+		for archive in &self.archives {
+			for script in &archive.scripts {
+				rows.push(table_row(&format!("  {}", script.name), &script.stats));
+			}
+			rows.push(table_row(&archive.name, &archive.total));
+		}
+		rows.push(table_row("Total", &self.total));
+
+		let widths: Vec<usize> = (0..TABLE_HEADER.len()).map(|col| rows.iter().map(|it| it[col].chars().count()).max().unwrap_or_default()).collect();
+		let mut out = String::new();
+		for row in &rows {
+			let cells: Vec<String> = row
+				.iter()
+				.zip(&widths)
+				.enumerate()
+				.map(|(col, (cell, width))| if col == 0 { format!("{cell:<width$}") } else { format!("{cell:>width$}") })
+				.collect();
+			writeln!(out, "{}", cells.join("  ").trim_end()).unwrap();
+		}
+
+		writeln!(out).unwrap();
+		let width = self.total.speakers.keys().map(|it| it.chars().count()).max().unwrap_or_default();
+		for (name, speaker) in &self.total.speakers {
+			writeln!(out, "{name:<width$}  {:>6} lines, {:.1}% translated", speaker.lines, percent(speaker.translated_lines, speaker.lines)).unwrap();
+		}
+		out
+	}
+
+	/// A summary to post: overall progress, progress per archive and the speakers with the most lines.
+	pub fn to_markdown(&self) -> String {
+		let total = &self.total;
+		let mut out = String::new();
+		writeln!(out, "## Translation progress").unwrap();
+		writeln!(out).unwrap();
+		writeln!(out, "**{:.1}%** of lines translated ({} of {}).", total.progress(), total.translated_lines, total.lines).unwrap();
+		writeln!(out).unwrap();
+		writeln!(out, "- Scene titles: {} of {} translated", total.translated_scene_titles, total.scene_titles).unwrap();
+		writeln!(out, "- Choices: {} of {} translated", total.choices - total.untranslated_choices, total.choices).unwrap();
+		writeln!(out, "- Lines with notes: {}", total.lines_with_notes).unwrap();
+		writeln!(out, "- Characters of original text: {}", total.raw_chars).unwrap();
+		writeln!(out).unwrap();
+
+		writeln!(out, "| Archive | Scripts | Lines | Translated | Progress |").unwrap();
+		writeln!(out, "|---|---:|---:|---:|---:|").unwrap();
+		for archive in &self.archives {
+			let it = &archive.total;
+			writeln!(out, "| {} | {} | {} | {} | {:.1}% |", archive.name, archive.scripts.len(), it.lines, it.translated_lines, it.progress()).unwrap();
+		}
+		writeln!(out).unwrap();
+
+		let mut speakers: Vec<_> = total.speakers.iter().collect();
+		speakers.sort_by(|a, b| b.1.lines.cmp(&a.1.lines).then(a.0.cmp(b.0)));
+		writeln!(out, "| Speaker | Lines | Progress |").unwrap();
+		writeln!(out, "|---|---:|---:|").unwrap();
+		for (name, it) in speakers {
+			writeln!(out, "| {name} | {} | {:.1}% |", it.lines, percent(it.translated_lines, it.lines)).unwrap();
+		}
+		out
+	}
+}
@@ -1,9 +1,8 @@
-use crate::opcodes::{Choice, OpField, Opcode, Script, TLString};
-use std::collections::HashMap;
+use crate::opcodes::{Choice, Instruction, Opcode, Script, TLString};
 
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until, take_while};
-use nom::combinator::{map_res, value};
+use nom::combinator::{map_res, opt, value};
 use nom::multi::{many0, separated_list0};
 use nom::sequence::{preceded, terminated};
 use nom::IResult;
@@ -16,176 +15,202 @@ use std::fmt::Formatter;
 const TL_CHOICE_END: Lazy<String> = Lazy::new(|| "---~~~---".to_string());
 const TL_LINE_END: Lazy<String> = Lazy::new(|| "---===---".to_string());
 
-pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: Vec<DocLine>) {
-	let mut addr2opcode: HashMap<usize, &mut Opcode> = HashMap::new();
-	for opcode in script.opcodes.iter_mut() {
-		if ![0x41, 0x42, 0xE0, 0x02].contains(&opcode.opcode) {
-			continue;
-		}
+/// Put in a translation to show the rest of it in a new textbox after this one.
+pub const SPLIT_MARKER: &str = "<split/>";
+/// Put in place of a translation to remove the textbox or scene title.
+pub const DELETE_MARKER: &str = "<delete/>";
+/// The suffix `escape_str` adds to textbox translations.
+const TEXTBOX_SUFFIX: &str = "%K%P";
 
-		match opcode.opcode {
-			0x41 | 0x42 | 0x02 | 0xE0 => {
-				addr2opcode.insert(opcode.address, opcode);
-			}
-			_ => {}
-		}
-	}
+/// The opcodes with lines in the translation document.
+const TEXT_OPCODES: [u8; 4] = [0x41, 0x42, 0xE0, 0x02];
 
+/// Applies a translation document to `script`. A translation of [`DELETE_MARKER`] deletes its textbox or scene title and
+/// [`SPLIT_MARKER`]s in a translation split it into several textboxes. Textboxes split off a line keep its address,
+/// so applying the same document again replaces them rather than splitting a second time.
+pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: Vec<DocLine>) {
 	for line in tl_doc.into_iter() {
-		match line {
-			DocLine::Line(line) => {
-				let opcode = addr2opcode.get_mut(&(line.address as usize)).unwrap();
-
-				if opcode.opcode == 0x41 {
-					match &mut opcode.fields[3] {
-						OpField::String(orig_str) => {
-							let _ = std::mem::replace(orig_str, line.translation);
-						}
-						_ => {}
-					};
-				}
+		let address = match &line {
+			DocLine::Line(line) | DocLine::Scene(line) => line.address,
+			DocLine::SpeakerLine(line) => line.address,
+			DocLine::Choices(choice) => choice.address,
+		} as usize;
+
+		// Instructions inserted after a line share its address, the original one comes first.
+		let Some(idx) = script.opcodes.iter().position(|it| it.address == address && TEXT_OPCODES.contains(&it.opcode)) else {
+			// A line deleted by an earlier run is expected to be gone.
+			if !line.is_deleted() {
+				log::warn!("No text at 0x{address:08X}, skipping its translation");
 			}
-			DocLine::Scene(line) => {
-				let opcode = addr2opcode.get_mut(&(line.address as usize)).unwrap();
-
-				if opcode.opcode == 0xE0 {
-					match &mut opcode.fields[0] {
-						OpField::String(orig_str) => {
-							let _ = std::mem::replace(orig_str, line.translation);
-						}
-						_ => {}
-					};
-				}
+			continue;
+		};
+		let Some(mut instruction) = Instruction::from_opcode(&script.opcodes[idx]) else {
+			continue;
+		};
+
+		match (&mut instruction, line) {
+			(Instruction::Text { text, .. }, DocLine::Line(line)) => *text = line.translation,
+			(Instruction::SceneTitle { title }, DocLine::Scene(line)) => *title = line.translation,
+			(Instruction::TextWithSpeaker { speaker, text, .. }, DocLine::SpeakerLine(line)) => {
+				*speaker = line.speaker_translation;
+				*text = line.translation;
 			}
-			DocLine::SpeakerLine(line) => {
-				let opcode = addr2opcode.get_mut(&(line.address as usize)).unwrap();
-				if opcode.opcode == 0x42 {
-					match &mut opcode.fields[4] {
-						OpField::String(orig_str) => {
-							let _ = std::mem::replace(orig_str, line.speaker_translation);
-						}
-						_ => {}
-					};
-
-					match &mut opcode.fields[5] {
-						OpField::String(orig_str) => {
-							let _ = std::mem::replace(orig_str, line.translation);
-						}
-						_ => {}
-					};
-				}
+			(Instruction::Choices { choices, .. }, DocLine::Choices(choice)) => {
+				choices
+					.iter_mut()
+					.zip(choice.choices)
+					.for_each(|(orig, new)| {
+						let _ = std::mem::replace(&mut orig.choice_str, new);
+					});
 			}
-			DocLine::Choices(choice) => {
-				let opcode = addr2opcode.get_mut(&(choice.address as usize)).unwrap();
-				if opcode.opcode == 0x02 {
-					match &mut opcode.fields[2] {
-						OpField::Choice(orig_choices) => {
-							orig_choices
-								.into_iter()
-								.zip(choice.choices.into_iter())
-								.for_each(|(orig, new)| {
-									let _ = std::mem::replace(&mut orig.choice_str, new);
-								});
-						}
-						_ => {}
-					};
+			_ => continue,
+		}
+
+		let parts = match &mut instruction {
+			Instruction::Text { text, .. } | Instruction::TextWithSpeaker { text, .. } => split_translation(text),
+			Instruction::SceneTitle { title } if title.translation.as_deref().is_some_and(is_delete_marker) => None,
+			_ => Some(vec![]),
+		};
+
+		script.opcodes[idx].fields = instruction.to_fields();
+		remove_split_parts(script, idx);
+
+		let Some(parts) = parts else {
+			if let Err(err) = script.delete(idx) {
+				log::error!("Cannot delete the text at 0x{address:08X}: {err}");
+			}
+			continue;
+		};
+
+		for (n, part) in parts.into_iter().enumerate() {
+			let mut opcode = script.opcodes[idx].clone();
+			opcode.label = None;
+			if let Some(mut part_instruction) = Instruction::from_opcode(&opcode) {
+				if let Instruction::Text { text, .. } | Instruction::TextWithSpeaker { text, .. } = &mut part_instruction {
+					*text = TLString { raw: String::new(), translation: Some(part), notes: None };
 				}
+				opcode.fields = part_instruction.to_fields();
 			}
+			script.insert(idx + 1 + n, opcode).unwrap();
 		}
 	}
 }
 
+/// Cuts a translation at its [`SPLIT_MARKER`]s, leaving the first part in `text` and returning the others, each
+/// ending a textbox like the whole translation did. Returns `None` if the textbox is to be deleted.
+fn split_translation(text: &mut TLString) -> Option<Vec<String>> {
+	let Some(translation) = &text.translation else {
+		return Some(vec![]);
+	};
+
+	if is_delete_marker(translation) {
+		return None;
+	}
+	let suffix = if translation.ends_with(TEXTBOX_SUFFIX) { TEXTBOX_SUFFIX } else { "" };
+	let body = &translation[..translation.len() - suffix.len()];
+	if !body.contains(SPLIT_MARKER) {
+		return Some(vec![]);
+	}
+
+	let mut parts: Vec<String> = body.split(SPLIT_MARKER).map(|it| format!("{}{suffix}", it.trim())).collect();
+	text.translation = Some(parts.remove(0));
+	Some(parts)
+}
+
+/// Whether a translation is only [`DELETE_MARKER`], leaving out the end of a textbox.
+fn is_delete_marker(translation: &str) -> bool {
+	translation.trim_end_matches(TEXTBOX_SUFFIX).trim() == DELETE_MARKER
+}
+
+/// Removes the textboxes an earlier run split off the textbox at `idx`.
+fn remove_split_parts(script: &mut Script, idx: usize) {
+	let Opcode { opcode, address, .. } = script.opcodes[idx];
+	while script.opcodes.get(idx + 1).is_some_and(|it| it.opcode == opcode && it.address == address) {
+		script.delete(idx + 1).unwrap();
+	}
+}
+
 pub fn tl_transform_script(input: &Script) -> String {
 	let mut lines = vec![];
+	for docline in script_doclines(input) {
+		lines.push(docline.to_string());
+		lines.push(TL_LINE_END.clone());
+		lines.push("\n\n\n".to_string());
+	}
+
+	lines.join("")
+}
+
+/// The lines of the translation document of `input`.
+pub fn script_doclines(input: &Script) -> Vec<DocLine> {
+	let mut doclines: Vec<DocLine> = vec![];
+	let mut last_text: Option<(u8, usize)> = None;
+	// 0x23 and 0x27 come right before the line they voice.
+	let mut voice: Option<String> = None;
 
 	for opcode in input.opcodes.iter() {
-		if ![0x41, 0x42, 0x02, 0xE0].contains(&opcode.opcode) {
+		let address = opcode.address as u32;
+		let instruction = Instruction::from_opcode(opcode);
+
+		// Textboxes split off a line go back into its translation.
+		if last_text == Some((opcode.opcode, opcode.address)) {
+			let part = match &instruction {
+				Some(Instruction::Text { text, .. } | Instruction::TextWithSpeaker { text, .. }) => text,
+				_ => continue,
+			};
+			let translation = match doclines.last_mut() {
+				Some(DocLine::Line(line)) => &mut line.translation.translation,
+				Some(DocLine::SpeakerLine(line)) => &mut line.translation.translation,
+				_ => continue,
+			};
+			let joined = translation.take().unwrap_or_default();
+			let joined = joined.trim_end_matches(TEXTBOX_SUFFIX);
+			*translation = Some(format!("{joined}{SPLIT_MARKER}{}", part.translation.as_deref().unwrap_or_default()));
 			continue;
 		}
 
-		match opcode.opcode {
-			0x42 => {
-				let address = opcode.address;
-				let mut thing = opcode.fields.iter().filter_map(|it| match it {
-					OpField::String(it) => Some(it),
-					_ => None,
-				});
-
-				let speaker_tl_string = thing.next().unwrap();
-				let tl_string = thing.next().unwrap();
-
-				let docline = DocLine::SpeakerLine(SpeakerLine {
-					speaker_translation: speaker_tl_string.clone(),
-					address: address as u32,
-					translation: tl_string.clone(),
-					speaker_address: address as u32,
-				});
-
-				lines.push(docline.to_string());
+		let docline = match instruction {
+			// An empty name stops the voice rather than playing one.
+			Some(Instruction::Voice { file, .. } | Instruction::Voice2 { file, .. }) => {
+				voice = Some(file.raw).filter(|it| !it.trim().is_empty());
+				continue;
 			}
+			Some(Instruction::TextWithSpeaker { speaker, text, .. }) => DocLine::SpeakerLine(SpeakerLine {
+				speaker_translation: speaker,
+				address,
+				translation: text,
+				speaker_address: address,
+				voice: voice.take(),
+			}),
 			// Scene title.
-			0xE0 => {
-				let address = opcode.address;
-				let tl_string = match &opcode.fields[0] {
-					OpField::String(orig_str) => orig_str.clone(),
-					_ => {
-						log::error!("Weird stuff happening to scene!");
-						continue;
-					}
-				};
-
-				let docline = DocLine::Scene(Line {
-					translation: tl_string,
-					address: address as u32,
-				});
-				lines.push(docline.to_string());
-			}
-
+			Some(Instruction::SceneTitle { title }) => DocLine::Scene(Line {
+				translation: title,
+				address,
+				voice: None,
+			}),
 			// Textbox with no speaker.
-			0x41 => {
-				let address = opcode.address;
-				let tl_string = opcode
-					.fields
-					.iter()
-					.find_map(|it| match it {
-						OpField::String(it) => Some(it),
-						_ => None,
-					})
-					.unwrap();
-
-				let docline = DocLine::Line(Line {
-					translation: tl_string.clone(),
-					address: address as u32,
-				});
-				lines.push(docline.to_string());
-			}
-			0x02 => {
-				let address = opcode.address;
-				let choices = opcode
-					.fields
-					.iter()
-					.find_map(|it| match it {
-						OpField::Choice(it) => Some(it),
-						_ => None,
-					})
-					.unwrap();
-
-				let docline = DocLine::Choices(ChoiceLine {
-					address: address as u32,
-					choices: choices
-						.iter()
-						.map(|Choice { choice_str, .. }| choice_str.clone())
-						.collect(),
-				});
-				lines.push(docline.to_string());
-			}
+			Some(Instruction::Text { text, .. }) => DocLine::Line(Line {
+				translation: text,
+				address,
+				voice: voice.take(),
+			}),
+			Some(Instruction::Choices { choices, .. }) => DocLine::Choices(ChoiceLine {
+				address,
+				choices: choices
+					.into_iter()
+					.map(|Choice { choice_str, .. }| choice_str)
+					.collect(),
+			}),
 			_ => continue,
-		}
-		lines.push(TL_LINE_END.clone());
-		lines.push("\n\n\n".to_string());
+		};
+
+		// A voice not played with the next textbox belongs to none.
+		voice = None;
+		last_text = Some((opcode.opcode, opcode.address));
+		doclines.push(docline);
 	}
 
-	lines.join("")
+	doclines
 }
 
 fn is_hex_digit_a(c: char) -> bool {
@@ -230,22 +255,26 @@ pub fn tltag(input: &str) -> IResult<&str, TLTag> {
 
 #[derive(Default, Debug)]
 pub struct Line {
-	address: u32,
-	translation: TLString,
+	pub(crate) address: u32,
+	pub(crate) translation: TLString,
+	/// The voice file played for the line, shown in the document but not read back.
+	pub(crate) voice: Option<String>,
 }
 
 #[derive(Default, Debug)]
 pub struct SpeakerLine {
-	address: u32,
-	speaker_address: u32,
-	speaker_translation: TLString,
-	translation: TLString,
+	pub(crate) address: u32,
+	pub(crate) speaker_address: u32,
+	pub(crate) speaker_translation: TLString,
+	pub(crate) translation: TLString,
+	/// The voice file played for the line, shown in the document but not read back.
+	pub(crate) voice: Option<String>,
 }
 
 #[derive(Default, Debug)]
 pub struct ChoiceLine {
-	address: u32,
-	choices: Vec<TLString>,
+	pub(crate) address: u32,
+	pub(crate) choices: Vec<TLString>,
 }
 
 #[derive(Debug)]
@@ -256,6 +285,18 @@ pub enum DocLine {
 	Scene(Line),
 }
 
+impl DocLine {
+	/// Whether the translation asks for the textbox or scene title to be deleted.
+	fn is_deleted(&self) -> bool {
+		let translation = match self {
+			DocLine::Line(line) | DocLine::Scene(line) => &line.translation,
+			DocLine::SpeakerLine(line) => &line.translation,
+			DocLine::Choices(_) => return false,
+		};
+		translation.translation.as_deref().is_some_and(is_delete_marker)
+	}
+}
+
 impl std::fmt::Display for DocLine {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		match self {
@@ -267,6 +308,7 @@ impl std::fmt::Display for DocLine {
 												 raw,
 												 notes: note_text,
 											 },
+											 ..
 										 }) => {
 				let translation = tl_text.as_ref().map(|it| it.as_str().trim()).unwrap_or_default();
 				let notes = note_text.as_ref().map(|it| it.as_str().trim()).unwrap_or_default();
@@ -283,11 +325,15 @@ impl std::fmt::Display for DocLine {
 												raw,
 												notes: note_text,
 											},
+											voice,
 										}) => {
 				let translation = tl_text.as_ref().map(|it| it.as_str().trim()).unwrap_or_default();
 				let notes = note_text.as_ref().map(|it| it.as_str().trim()).unwrap_or_default();
 
 				write!(f, "[original text @ 0x{address:08X}]: {raw}\n")?;
+				if let Some(voice) = voice {
+					write!(f, "[voice]: {voice}\n")?;
+				}
 				write!(f, "[translation]: {translation}\n")?;
 				write!(f, "[notes]: {notes}\n")
 			}
@@ -306,6 +352,7 @@ impl std::fmt::Display for DocLine {
 															 raw,
 															 notes: note_text,
 														 },
+														 voice,
 													 }) => {
 				let speaker_tl_text = speaker_translation
 					.as_ref()
@@ -316,6 +363,9 @@ impl std::fmt::Display for DocLine {
 
 				write!(f, "[speaker @ 0x{address:08X}]: {speaker_tl_text} ({speaker_raw})\n")?;
 				write!(f, "[original text @ 0x{speaker_address:08X}]: {raw}\n")?;
+				if let Some(voice) = voice {
+					write!(f, "[voice]: {voice}\n")?;
+				}
 				write!(f, "[translation]: {translation}\n")?;
 				write!(f, "[notes]: {notes}\n")
 			}
@@ -473,6 +523,9 @@ pub fn parse_docline_group(input: &str) -> IResult<&str, DocLine> {
 		}
 	};
 
+	// The voice file is only there for reference.
+	let (rest, _) = opt(preceded(tag("\n[voice]:"), take_until("\n["))).parse(rest)?;
+
 	let (rest, (tl, notes)) = terminated(
 		alt((
 			(
@@ -0,0 +1,88 @@
+//! Checks that decoding a script and encoding it again through the YAML the tools write gives back the same bytes.
+
+use crate::data::{decode_wsc, fix_yaml_str};
+use crate::opcodes::{opcode_spec, EncodeError, Script};
+
+/// The instruction a differing byte belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainingOpcode {
+	pub address: usize,
+	pub opcode: u8,
+	pub mnemonic: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+	Yaml(String),
+	Encode(EncodeError),
+	/// The first byte that differs. `expected` or `found` is `None` past the end of the original or the encoded script,
+	/// and `opcode` is `None` past the last instruction.
+	Mismatch { offset: usize, expected: Option<u8>, found: Option<u8>, opcode: Option<ContainingOpcode> },
+}
+
+impl std::fmt::Display for VerifyError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let byte = |it: &Option<u8>| it.map_or("end of script".to_string(), |it| format!("0x{it:02X}"));
+		match self {
+			VerifyError::Yaml(error) => write!(f, "YAML round trip failed: {error}"),
+			VerifyError::Encode(error) => write!(f, "cannot encode: {error}"),
+			VerifyError::Mismatch { offset, expected, found, opcode } => {
+				write!(f, "differs at 0x{offset:08X}, expected {} but found {}", byte(expected), byte(found))?;
+				match opcode {
+					Some(ContainingOpcode { address, opcode, mnemonic }) => {
+						write!(f, ", in opcode 0x{opcode:02X} {} at 0x{address:08X}", mnemonic.unwrap_or("(unknown)"))
+					}
+					None => write!(f, ", after the last instruction"),
+				}
+			}
+		}
+	}
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Decodes `input`, writes it as YAML, reads that back and encodes it, then compares the bytes with `input`.
+pub fn verify_wsc(input: &[u8]) -> Result<(), VerifyError> {
+	let script = decode_wsc(input);
+	let yaml = fix_yaml_str(serde_yml::to_string(&script).map_err(|it| VerifyError::Yaml(it.to_string()))?);
+	// libyml panics on scalars it cannot read back, which fails this script rather than the whole check.
+	let read_back: Script = std::panic::catch_unwind(|| serde_yml::from_str(&yaml))
+		.map_err(|_| VerifyError::Yaml("the YAML reader panicked".to_string()))?
+		.map_err(|it| VerifyError::Yaml(it.to_string()))?;
+	let output = read_back.binary_serialise().map_err(VerifyError::Encode)?;
+
+	let Some(offset) = (0..input.len().max(output.len())).find(|it| input.get(*it) != output.get(*it)) else {
+		return Ok(());
+	};
+
+	let opcode = script
+		.opcodes
+		.iter()
+		.take_while(|it| it.address <= offset)
+		.last()
+		.filter(|it| offset < it.address + it.size())
+		.map(|it| ContainingOpcode { address: it.address, opcode: it.opcode, mnemonic: opcode_spec(it.opcode).map(|it| it.mnemonic) });
+	Err(VerifyError::Mismatch { offset, expected: input.get(offset).copied(), found: output.get(offset).copied(), opcode })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::opcodes::{OpField, TLString};
+
+	#[test]
+	fn text_with_long_runs_of_spaces_round_trips() {
+		for spaces in [1, 3, 15, 16, 20, 40] {
+			let raw = format!("a{}b 「{}」 '{}", " ".repeat(spaces), " ".repeat(spaces), " ".repeat(spaces));
+			let mut text = opcode_spec(0x41).unwrap().sample(0);
+			for field in &mut text.fields {
+				if let OpField::String(string) = field {
+					*string = TLString { raw: raw.clone(), translation: None, notes: None };
+				}
+			}
+			let end = opcode_spec(0xFF).unwrap().sample(text.size());
+			let input = Script { opcodes: vec![text, end], trailer: vec![] }.binary_serialise().unwrap();
+			assert_eq!(verify_wsc(&input), Ok(()), "{raw:?}");
+		}
+	}
+}
@@ -0,0 +1,741 @@
+use crate::lzss;
+use crate::util::{ends_with_ignore_case, to_bytes};
+use camino::Utf8Path;
+use nom::bytes::complete::tag;
+use nom::combinator::map;
+use nom::multi::count;
+use nom::number::complete::{le_u16, le_u32};
+use nom::{IResult, Parser};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+pub struct WIPFHeader {
+	n_entries: u16,
+	depth: u16,
+}
+
+impl WIPFHeader {
+	const SIZE: usize = 8;
+
+	fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+		map((tag("WIPF".as_bytes()), le_u16, le_u16), |(_, n_entries, depth)| Self { n_entries, depth }).parse(input)
+	}
+}
+
+#[repr(C, packed)]
+pub struct BMPHeader {
+	magic: [u8; 2],
+	filesz: u32,
+	res1: u16,
+	res2: u16,
+	offset: u32,
+}
+
+#[repr(C, packed)]
+pub struct BMPDibV3Header {
+	header_sz: u32,
+	width: u32,
+	height: u32,
+	nplanes: u16,
+	depth: u16,
+	compress_type: u32,
+	bmp_bytesz: u32,
+	hres: u32,
+	vres: u32,
+	ncolors: u32,
+	nimpcolors: u32,
+}
+
+pub struct WIPFENTRY {
+	width: u32,     // unsigned long  width;    // ����
+	height: u32,    // unsigned long  height;   // �߶�
+	x_offset: u32,  // unsigned long  offset_x; // x������ʾλ��
+	y_offset: u32,  // unsigned long  offset_y; // y������ʾλ��
+	unk_layer: u32, // unsigned long  unknown1; // layer?
+	length: u32,    // unsigned long  length;   // �ļ�����
+}
+
+impl WIPFENTRY {
+	const SIZE: usize = 24;
+
+	fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+		map(
+			(le_u32, le_u32, le_u32, le_u32, le_u32, le_u32),
+			|(width, height, x_offset, y_offset, unk_layer, length)| Self { width, height, x_offset, y_offset, unk_layer, length },
+		)
+			.parse(input)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WipfError {
+	/// The file is too short to hold its header and entry table.
+	Truncated { needed: usize, available: usize },
+	BadSignature([u8; 4]),
+	BadDepth(u16),
+	/// Only 8 bit images carry a palette.
+	NotPaletted(u16),
+	/// An entry with no pixels, or larger than any image could be.
+	BadDimensions { entry: usize, width: u32, height: u32 },
+	/// The palette or compressed data of an entry runs past the end of the file.
+	EntryOutOfBounds { entry: usize, offset: usize, length: usize, available: usize },
+}
+
+impl std::fmt::Display for WipfError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WipfError::Truncated { needed, available } => {
+				write!(f, "file is truncated, the header and entry table need 0x{needed:X} bytes but only 0x{available:X} are present")
+			}
+			WipfError::BadSignature(signature) => write!(f, "bad signature {signature:02X?}, expected \"WIPF\""),
+			WipfError::BadDepth(depth) => write!(f, "unsupported depth {depth}, expected 8, 24 or 32"),
+			WipfError::NotPaletted(depth) => write!(f, "image has depth {depth}, only 8 bit images have a palette"),
+			WipfError::BadDimensions { entry, width, height } => write!(f, "entry {entry} has unusable dimensions {width}x{height}"),
+			WipfError::EntryOutOfBounds { entry, offset, length, available } => write!(
+				f,
+				"entry {entry} needs 0x{length:X} bytes at data offset 0x{offset:X} but only 0x{available:X} bytes of data are present"
+			),
+		}
+	}
+}
+
+impl std::error::Error for WipfError {}
+
+/// A single decompressed image from a WIPF file.
+#[derive(Debug, Clone)]
+pub struct WipfImage {
+	pub width: u32,
+	pub height: u32,
+	pub x_offset: u32,
+	pub y_offset: u32,
+	pub unk_layer: u32,
+	pub depth: u16,
+	/// 1024 byte BGRA palette for 8 bit images, empty otherwise.
+	pub palette: Vec<u8>,
+	/// Top-down pixel data. Palette indices for 8 bit images, interleaved BGR(A) otherwise.
+	pub pixels: Vec<u8>,
+}
+
+impl WipfImage {
+	pub fn bytes_per_pixel(&self) -> usize {
+		self.depth as usize / 8
+	}
+
+	/// Converts the image into top-down RGBA pixels.
+	pub fn to_rgba(&self) -> Vec<u8> {
+		let n_pixels = (self.width * self.height) as usize;
+		let mut out = Vec::with_capacity(n_pixels * 4);
+
+		for idx in 0..n_pixels {
+			let bgra = match self.depth {
+				8 => {
+					let entry = self.pixels[idx] as usize * 4;
+					[self.palette[entry], self.palette[entry + 1], self.palette[entry + 2], 0xFF]
+				}
+				24 => [self.pixels[idx * 3], self.pixels[idx * 3 + 1], self.pixels[idx * 3 + 2], 0xFF],
+				_ => [self.pixels[idx * 4], self.pixels[idx * 4 + 1], self.pixels[idx * 4 + 2], self.pixels[idx * 4 + 3]],
+			};
+			out.extend([bgra[2], bgra[1], bgra[0], bgra[3]]);
+		}
+
+		out
+	}
+
+	/// Reads a single channel of the image as an alpha mask, as MSK files are stored.
+	pub fn to_alpha(&self) -> Vec<u8> {
+		let n_pixels = (self.width * self.height) as usize;
+		match self.depth {
+			8 => self.pixels[..n_pixels].iter().map(|&idx| self.palette[idx as usize * 4]).collect(),
+			_ => self.pixels.iter().step_by(self.bytes_per_pixel()).copied().collect(),
+		}
+	}
+
+	/// Serialises the image as a bottom-up BMP file, keeping the palette of 8 bit images.
+	pub fn to_bmp(&self) -> Vec<u8> {
+		let bpp = self.bytes_per_pixel();
+		let line_len = self.width as usize * bpp;
+		let stride = (line_len + 3) & !3usize;
+
+		let mut image_data = Vec::with_capacity(stride * self.height as usize);
+		for line in self.pixels.chunks(line_len).take(self.height as usize).rev() {
+			image_data.extend(line);
+			image_data.extend(vec![0u8; stride - line_len]);
+		}
+
+		let (file_size, bmp_offset, imgdata_size) = if self.depth == 8 {
+			(0x436 + image_data.len(), 0x436, 0x400 + image_data.len())
+		} else {
+			(0x36 + image_data.len(), 0x36, image_data.len())
+		};
+
+		let bmp_header = BMPHeader {
+			magic: [b'B', b'M'],
+			filesz: file_size as u32,
+			res1: 0,
+			res2: 0,
+			offset: bmp_offset,
+		};
+
+		let bmp_dib_header = BMPDibV3Header {
+			header_sz: 0x28,
+			width: self.width,
+			height: self.height,
+			nplanes: 1,
+			bmp_bytesz: imgdata_size as u32,
+			depth: self.depth,
+			compress_type: 0,
+			hres: 0,
+			vres: 0,
+			ncolors: 0,
+			nimpcolors: 0,
+		};
+
+		to_bytes(&bmp_header)
+			.iter()
+			.chain(to_bytes(&bmp_dib_header))
+			.chain(&self.palette)
+			.chain(&image_data)
+			.copied()
+			.collect()
+	}
+
+	/// The name used for this image when it is written out on its own.
+	pub fn output_name(&self, filename: &str, entry_no: usize, extension: &str) -> String {
+		format!("{filename}_{entry_no:03}+{}x{}y.{extension}", self.x_offset, self.y_offset)
+	}
+}
+
+/// The largest width or height an entry can have, far above any image of the game, so a corrupt header cannot make
+/// decoding allocate gigabytes.
+const MAX_DIMENSION: u32 = 4096;
+
+/// Checks the header and entry table of a WIPF file, returning them along with the image data that follows.
+fn parse_wipf(content: &[u8]) -> Result<(WIPFHeader, Vec<WIPFENTRY>, &[u8]), WipfError> {
+	if content.len() >= 4 && &content[..4] != "WIPF".as_bytes() {
+		return Err(WipfError::BadSignature(content[..4].try_into().unwrap()));
+	}
+
+	let truncated = |n_entries: usize| WipfError::Truncated {
+		needed: WIPFHeader::SIZE + n_entries * WIPFENTRY::SIZE,
+		available: content.len(),
+	};
+
+	let (rest, header) = WIPFHeader::parse(content).map_err(|_| truncated(0))?;
+	if ![8, 24, 32].contains(&header.depth) {
+		return Err(WipfError::BadDepth(header.depth));
+	}
+
+	let (data, entries) = count(WIPFENTRY::parse, header.n_entries as usize)
+		.parse(rest)
+		.map_err(|_: nom::Err<nom::error::Error<&[u8]>>| truncated(header.n_entries as usize))?;
+
+	let palette_len = if header.depth == 8 { 1024 } else { 0 };
+	let mut data_ptr = 0usize;
+	for (idx, entry) in entries.iter().enumerate() {
+		if !(1..=MAX_DIMENSION).contains(&entry.width) || !(1..=MAX_DIMENSION).contains(&entry.height) {
+			return Err(WipfError::BadDimensions { entry: idx, width: entry.width, height: entry.height });
+		}
+
+		let length = palette_len + entry.length as usize;
+		if data.len() < data_ptr + length {
+			return Err(WipfError::EntryOutOfBounds { entry: idx, offset: data_ptr, length, available: data.len() });
+		}
+		data_ptr += length;
+	}
+
+	Ok((header, entries, data))
+}
+
+/// Finds the palette of every entry of an 8 bit WIPF file, as offsets into `content`. Each palette is 1024 bytes of BGRA.
+pub fn palette_offsets(content: &[u8]) -> Result<Vec<usize>, WipfError> {
+	let (header, entries, data) = parse_wipf(content)?;
+	if header.depth != 8 {
+		return Err(WipfError::NotPaletted(header.depth));
+	}
+
+	let mut offset = content.len() - data.len();
+	Ok(entries
+		.iter()
+		.map(|entry| {
+			let palette_offset = offset;
+			offset += 1024 + entry.length as usize;
+			palette_offset
+		})
+		.collect())
+}
+
+/// Decompresses every entry of a WIPF file.
+pub fn decode_wipf(filename: &str, content: &[u8]) -> Result<Vec<WipfImage>, WipfError> {
+	let (header, entries, data) = parse_wipf(content)?;
+	let depth = header.depth;
+
+	log::warn!(
+		"WIPF file {filename} has {} entries with depth {}.",
+		entries.len(),
+		u32::from(depth)
+	);
+
+	let mut data_ptr = 0usize;
+	let mut images = vec![];
+	for (entry_no, entry) in entries.iter().enumerate() {
+		let (width, height) = (entry.width, entry.height);
+		log::warn!("    entry is {width}x{height}");
+
+		let palette = if depth == 8 {
+			let palette = &data[data_ptr..data_ptr + 1024];
+			data_ptr += 1024;
+			palette.to_vec()
+		} else {
+			vec![]
+		};
+
+		let bpp = depth as usize / 8;
+		let n_pixels = (width * height) as usize;
+		let (out_buf, status) = lzss::decode(&data[data_ptr..(data_ptr + entry.length as usize)], n_pixels * bpp);
+		data_ptr += entry.length as usize;
+
+		if status.produced < out_buf.len() {
+			log::warn!(
+				"Entry {entry_no} of {filename} ran out of data after 0x{:X} of 0x{:X} bytes, the rest is left blank.",
+				status.produced,
+				out_buf.len()
+			);
+		}
+
+		// Colour images are stored as one plane per channel.
+		let pixels = if bpp > 1 {
+			let mut interleaved = vec![0u8; out_buf.len()];
+			for (channel, plane) in out_buf.chunks(n_pixels).enumerate() {
+				for (idx, value) in plane.iter().enumerate() {
+					interleaved[idx * bpp + channel] = *value;
+				}
+			}
+			interleaved
+		} else {
+			out_buf
+		};
+
+		images.push(WipfImage {
+			width,
+			height,
+			x_offset: entry.x_offset,
+			y_offset: entry.y_offset,
+			unk_layer: entry.unk_layer,
+			depth,
+			palette,
+			pixels,
+		});
+	}
+
+	Ok(images)
+}
+
+/// Builds a WIPF file out of a set of images, which must all share the given depth.
+pub fn encode_wipf(depth: u16, images: &[WipfImage]) -> Vec<u8> {
+	let mut header = vec![];
+	let mut data = vec![];
+
+	header.extend(b"WIPF");
+	header.extend((images.len() as u16).to_le_bytes());
+	header.extend(depth.to_le_bytes());
+
+	for image in images {
+		let bpp = depth as usize / 8;
+		let n_pixels = (image.width * image.height) as usize;
+
+		let planar = if bpp > 1 {
+			let mut planar = vec![0u8; n_pixels * bpp];
+			for (idx, pixel) in image.pixels.chunks(bpp).enumerate() {
+				for (channel, value) in pixel.iter().enumerate() {
+					planar[channel * n_pixels + idx] = *value;
+				}
+			}
+			planar
+		} else {
+			image.pixels.clone()
+		};
+
+		let compressed = lzss::encode(&planar);
+
+		for field in [image.width, image.height, image.x_offset, image.y_offset, image.unk_layer, compressed.len() as u32] {
+			header.extend(field.to_le_bytes());
+		}
+
+		if depth == 8 {
+			data.extend(&image.palette);
+		}
+		data.extend(compressed);
+	}
+
+	header.extend(data);
+	header
+}
+
+/// Merges a WIP image with its MSK counterpart into RGBA pixels. Returns `None` if their sizes differ.
+pub fn combine_wip_msk(wip: &WipfImage, msk: &WipfImage) -> Option<Vec<u8>> {
+	if (wip.width, wip.height) != (msk.width, msk.height) {
+		return None;
+	}
+
+	let mut rgba = wip.to_rgba();
+	for (pixel, alpha) in rgba.chunks_mut(4).zip(msk.to_alpha()) {
+		pixel[3] = alpha;
+	}
+
+	Some(rgba)
+}
+
+/// Splits RGBA pixels into a 24 bit WIP image and an 8 bit greyscale MSK image with the given placement.
+pub fn split_wip_msk(width: u32, height: u32, rgba: &[u8], x_offset: u32, y_offset: u32, unk_layer: u32) -> (WipfImage, WipfImage) {
+	let bgr = rgba.chunks(4).flat_map(|px| [px[2], px[1], px[0]]).collect();
+	let alpha = rgba.chunks(4).map(|px| px[3]).collect();
+	let grey_palette = (0..=255u8).flat_map(|it| [it, it, it, 0]).collect();
+
+	let wip = WipfImage { width, height, x_offset, y_offset, unk_layer, depth: 24, palette: vec![], pixels: bgr };
+	let msk = WipfImage { width, height, x_offset, y_offset, unk_layer, depth: 8, palette: grey_palette, pixels: alpha };
+
+	(wip, msk)
+}
+
+/// Turns a 24 bit entry from [`split_wip_msk`] into one of `depth`. 8 bit entries use the colour of `palette`, 1024
+/// bytes of BGRA, nearest to each pixel, and 32 bit entries take their alpha from the MSK entry.
+pub fn convert_wip_depth(wip: WipfImage, msk: &WipfImage, depth: u16, palette: &[u8]) -> WipfImage {
+	let pixels = match depth {
+		8 => {
+			let colours: Vec<&[u8]> = palette.chunks(4).collect();
+			let mut nearest: HashMap<&[u8], u8> = HashMap::new();
+			wip.pixels
+				.chunks(3)
+				.map(|bgr| {
+					*nearest.entry(bgr).or_insert_with(|| {
+						let distance = |colour: &[u8]| colour.iter().zip(bgr).map(|(a, b)| (*a as i32 - *b as i32).pow(2)).sum::<i32>();
+						(0..colours.len()).min_by_key(|it| distance(colours[*it])).unwrap_or_default() as u8
+					})
+				})
+				.collect()
+		}
+		32 => wip.pixels.chunks(3).zip(&msk.pixels).flat_map(|(bgr, alpha)| [bgr[0], bgr[1], bgr[2], *alpha]).collect(),
+		_ => return wip,
+	};
+
+	let palette = if depth == 8 { palette.to_vec() } else { vec![] };
+	WipfImage { depth, palette, pixels, ..wip }
+}
+
+pub fn write_png(path: &Utf8Path, width: u32, height: u32, rgba: &[u8]) {
+	let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+	let mut encoder = png::Encoder::new(file, width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.write_header().unwrap().write_image_data(rgba).unwrap();
+}
+
+/// Reads a PNG file into top-down RGBA pixels, returning `(width, height, pixels)`.
+pub fn read_png_rgba(input: &[u8]) -> (u32, u32, Vec<u8>) {
+	let mut decoder = png::Decoder::new(std::io::Cursor::new(input));
+	decoder.set_transformations(png::Transformations::normalize_to_color8());
+	let mut reader = decoder.read_info().unwrap();
+	let mut buf = vec![0u8; reader.output_buffer_size().unwrap()];
+	let info = reader.next_frame(&mut buf).unwrap();
+	buf.truncate(info.buffer_size());
+
+	let rgba = match info.color_type {
+		png::ColorType::Rgba => buf,
+		png::ColorType::Rgb => buf.chunks(3).flat_map(|px| [px[0], px[1], px[2], 0xFF]).collect(),
+		png::ColorType::GrayscaleAlpha => buf.chunks(2).flat_map(|px| [px[0], px[0], px[0], px[1]]).collect(),
+		_ => buf.iter().flat_map(|&px| [px, px, px, 0xFF]).collect(),
+	};
+
+	(info.width, info.height, rgba)
+}
+
+fn is_wipf(content: &[u8]) -> bool {
+	content.len() >= 4 && &content[..4] == "WIPF".as_bytes()
+}
+
+fn stem(filename: &str) -> &str {
+	filename.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(filename)
+}
+
+/// How the entries of a WIPF file are laid out when extracted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipfLayout {
+	/// One BMP per entry, named after its offsets. Entries with an alpha mask are still written as PNGs.
+	Bmp,
+	/// One PNG per entry, named after its offsets.
+	Png,
+	/// Every entry drawn onto a single canvas at its offsets.
+	Composite,
+	/// Every entry packed into a sprite sheet, with a JSON atlas describing where each entry went.
+	Atlas,
+}
+
+impl std::str::FromStr for WipfLayout {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"bmp" => Ok(WipfLayout::Bmp),
+			"png" => Ok(WipfLayout::Png),
+			"composite" => Ok(WipfLayout::Composite),
+			"atlas" => Ok(WipfLayout::Atlas),
+			_ => Err(format!("unknown image layout {s}, expected one of bmp, png, composite or atlas")),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WipfAtlasEntry {
+	pub entry: usize,
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+	pub x_offset: u32,
+	pub y_offset: u32,
+	pub unk_layer: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WipfAtlas {
+	pub file: String,
+	pub image: String,
+	pub width: u32,
+	pub height: u32,
+	pub entries: Vec<WipfAtlasEntry>,
+}
+
+/// Copies `rgba` into `canvas` at `(x, y)`, blending it over what is already there. Neither is premultiplied, so
+/// the colours are weighted by alpha and divided by the alpha of the result.
+pub fn blit(canvas: &mut [u8], canvas_width: u32, rgba: &[u8], width: u32, x: u32, y: u32) {
+	for (row, line) in rgba.chunks(width as usize * 4).enumerate() {
+		let start = ((y as usize + row) * canvas_width as usize + x as usize) * 4;
+		for (dst, src) in canvas[start..start + line.len()].chunks_mut(4).zip(line.chunks(4)) {
+			// Alphas scaled by 255, so the blend stays in integers.
+			let src_alpha = src[3] as u32 * 255;
+			let dst_alpha = dst[3] as u32 * (255 - src[3] as u32);
+			let out_alpha = src_alpha + dst_alpha;
+			if out_alpha == 0 {
+				dst.copy_from_slice(&[0; 4]);
+				continue;
+			}
+			for channel in 0..3 {
+				dst[channel] = ((src[channel] as u32 * src_alpha + dst[channel] as u32 * dst_alpha + out_alpha / 2) / out_alpha) as u8;
+			}
+			dst[3] = ((out_alpha + 127) / 255) as u8;
+		}
+	}
+}
+
+/// Draws every entry onto one canvas at its offsets, in entry order. Returns `(width, height, pixels)`.
+pub fn composite_wipf(images: &[WipfImage], rgba: &[Vec<u8>]) -> (u32, u32, Vec<u8>) {
+	let width = images.iter().map(|it| it.x_offset + it.width).max().unwrap_or_default();
+	let height = images.iter().map(|it| it.y_offset + it.height).max().unwrap_or_default();
+
+	let mut canvas = vec![0u8; (width * height) as usize * 4];
+	for (image, pixels) in images.iter().zip(rgba) {
+		blit(&mut canvas, width, pixels, image.width, image.x_offset, image.y_offset);
+	}
+
+	(width, height, canvas)
+}
+
+/// Packs every entry into rows of a sprite sheet, returning the atlas describing it and its pixels.
+pub fn pack_wipf_atlas(filename: &str, images: &[WipfImage], rgba: &[Vec<u8>]) -> (WipfAtlas, Vec<u8>) {
+	let area = images.iter().map(|it| (it.width * it.height) as f64).sum::<f64>();
+	let sheet_width = images
+		.iter()
+		.map(|it| it.width)
+		.max()
+		.unwrap_or_default()
+		.max(area.sqrt().ceil() as u32);
+
+	let (mut x, mut y, mut row_height) = (0u32, 0u32, 0u32);
+	let mut entries = vec![];
+	for (entry, image) in images.iter().enumerate() {
+		if x + image.width > sheet_width {
+			x = 0;
+			y += row_height;
+			row_height = 0;
+		}
+
+		entries.push(WipfAtlasEntry {
+			entry,
+			x,
+			y,
+			width: image.width,
+			height: image.height,
+			x_offset: image.x_offset,
+			y_offset: image.y_offset,
+			unk_layer: image.unk_layer,
+		});
+
+		x += image.width;
+		row_height = row_height.max(image.height);
+	}
+
+	let sheet_height = y + row_height;
+	let mut sheet = vec![0u8; (sheet_width * sheet_height) as usize * 4];
+	for (entry, pixels) in entries.iter().zip(rgba) {
+		blit(&mut sheet, sheet_width, pixels, entry.width, entry.x, entry.y);
+	}
+
+	let atlas = WipfAtlas {
+		file: filename.to_string(),
+		image: format!("{filename}.atlas.png"),
+		width: sheet_width,
+		height: sheet_height,
+		entries,
+	};
+
+	(atlas, sheet)
+}
+
+/// A decoded WIPF file from an archive.
+pub struct ArchiveWipf<'a> {
+	pub filename: &'a str,
+	pub images: Vec<WipfImage>,
+	/// The RGBA pixels of every entry, if a matching MSK file was merged in as the alpha channel.
+	pub masked_rgba: Option<Vec<Vec<u8>>>,
+}
+
+impl ArchiveWipf<'_> {
+	/// The RGBA pixels of every entry, with the mask applied if there is one.
+	pub fn rgba(&self) -> Vec<Vec<u8>> {
+		self.masked_rgba.clone().unwrap_or_else(|| self.images.iter().map(WipfImage::to_rgba).collect())
+	}
+}
+
+/// Decodes every WIPF file of an archive, in archive order. WIP files with a matching MSK get the mask as their
+/// alpha channel, and the MSK is not returned on its own. Files that fail to decode are logged and left out.
+pub fn decode_archive_wipfs<'a>(filenames: &'a [String], contents: &[&[u8]]) -> Vec<ArchiveWipf<'a>> {
+	let find_file = |name: &str| filenames.iter().position(|it| it.eq_ignore_ascii_case(name));
+	let mut masked = HashMap::new();
+	let mut merged_masks = HashSet::new();
+
+	for (idx, (filename, content)) in filenames.iter().zip(contents).enumerate() {
+		if !ends_with_ignore_case(filename, &".WIP") || !is_wipf(content) {
+			continue;
+		}
+
+		let Some(msk_idx) = find_file(&format!("{}.MSK", stem(filename))).filter(|&it| is_wipf(contents[it])) else {
+			continue;
+		};
+
+		let msk_name = &filenames[msk_idx];
+		let (Some(images), Some(msk_images)) = (
+			try_decode_wipf(filename, content),
+			try_decode_wipf(msk_name, contents[msk_idx]),
+		) else {
+			continue;
+		};
+
+		if let Some(rgba) = apply_mask(filename, &images, msk_name, &msk_images) {
+			masked.insert(idx, (images, rgba));
+			merged_masks.insert(msk_idx);
+		}
+	}
+
+	let mut out = vec![];
+	for (idx, (filename, content)) in filenames.iter().zip(contents).enumerate() {
+		if merged_masks.contains(&idx) || !is_wipf(content) {
+			continue;
+		}
+
+		let (images, masked_rgba) = match masked.remove(&idx) {
+			Some((images, rgba)) => (images, Some(rgba)),
+			None => match try_decode_wipf(filename, content) {
+				Some(images) => (images, None),
+				None => continue,
+			},
+		};
+
+		out.push(ArchiveWipf { filename, images, masked_rgba });
+	}
+
+	out
+}
+
+/// Extracts every WIPF image of an archive into `out_folder`. WIP files with a matching MSK get the mask as their
+/// alpha channel.
+pub fn extract_wipfs(filenames: &[String], contents: &[&[u8]], out_folder: &Utf8Path, layout: WipfLayout) {
+	for wipf in decode_archive_wipfs(filenames, contents) {
+		write_wipf_images(wipf.filename, &wipf.images, wipf.masked_rgba, out_folder, layout);
+	}
+}
+
+/// Decodes a WIPF file, logging instead of failing so one bad image does not stop the rest of an archive.
+fn try_decode_wipf(filename: &str, content: &[u8]) -> Option<Vec<WipfImage>> {
+	decode_wipf(filename, content)
+		.inspect_err(|err| log::error!("Could not decode WIPF file {filename}: {err}"))
+		.ok()
+}
+
+fn apply_mask(wip_name: &str, wip_images: &[WipfImage], msk_name: &str, msk_images: &[WipfImage]) -> Option<Vec<Vec<u8>>> {
+	if wip_images.len() != msk_images.len() {
+		log::warn!("{wip_name} and {msk_name} have different entry counts, extracting them separately.");
+		return None;
+	}
+
+	let combined = wip_images
+		.iter()
+		.zip(msk_images)
+		.map(|(wip, msk)| combine_wip_msk(wip, msk))
+		.collect::<Option<Vec<_>>>();
+
+	if combined.is_none() {
+		log::warn!("{wip_name} and {msk_name} have mismatched entry sizes, extracting them separately.");
+	}
+
+	combined
+}
+
+fn write_wipf_images(filename: &str, images: &[WipfImage], rgba: Option<Vec<Vec<u8>>>, out_folder: &Utf8Path, layout: WipfLayout) {
+	match layout {
+		WipfLayout::Bmp | WipfLayout::Png => {
+			let output_file_path = out_folder.join(filename);
+			std::fs::create_dir_all(&output_file_path).unwrap();
+			for (entry_no, image) in images.iter().enumerate() {
+				match &rgba {
+					Some(rgba) => {
+						let out_file = output_file_path.join(image.output_name(filename, entry_no, "png"));
+						write_png(&out_file, image.width, image.height, &rgba[entry_no]);
+					}
+					None if layout == WipfLayout::Png => {
+						let out_file = output_file_path.join(image.output_name(filename, entry_no, "png"));
+						write_png(&out_file, image.width, image.height, &image.to_rgba());
+					}
+					None => {
+						let out_file = output_file_path.join(image.output_name(filename, entry_no, "bmp"));
+						std::fs::write(out_file, image.to_bmp()).unwrap();
+					}
+				}
+			}
+		}
+		WipfLayout::Composite => {
+			let rgba = rgba.unwrap_or_else(|| images.iter().map(WipfImage::to_rgba).collect());
+			let (width, height, canvas) = composite_wipf(images, &rgba);
+			std::fs::create_dir_all(out_folder).unwrap();
+			write_png(&out_folder.join(format!("{filename}.png")), width, height, &canvas);
+		}
+		WipfLayout::Atlas => {
+			let rgba = rgba.unwrap_or_else(|| images.iter().map(WipfImage::to_rgba).collect());
+			let (atlas, sheet) = pack_wipf_atlas(filename, images, &rgba);
+			std::fs::create_dir_all(out_folder).unwrap();
+			write_png(&out_folder.join(&atlas.image), atlas.width, atlas.height, &sheet);
+			let atlas_json = serde_json::to_string_pretty(&atlas).unwrap();
+			std::fs::write(out_folder.join(format!("{filename}.atlas.json")), atlas_json).unwrap();
+		}
+	}
+}
+
+/// Parses the entry number and offsets out of a name produced by [`WipfImage::output_name`].
+/// Returns `(filename, entry_no, x_offset, y_offset)`.
+pub fn parse_output_name(name: &str) -> Option<(&str, usize, u32, u32)> {
+	let name = stem(name);
+	let (filename, placement) = name.rsplit_once('_')?;
+	let (entry_no, offsets) = placement.split_once('+')?;
+	let (x_offset, y_offset) = offsets.strip_suffix('y')?.split_once('x')?;
+
+	Some((filename, entry_no.parse().ok()?, x_offset.parse().ok()?, y_offset.parse().ok()?))
+}
@@ -0,0 +1,238 @@
+//! Runs decoded scripts without the game, to read a route in order. Only control flow, the variable table, text,
+//! scene titles and choices are carried out. Every other opcode is logged and skipped.
+//!
+//! Two things are guesses, as `opcodes.md` does not know them yet:
+//! - 0x03 types 0, 1 and 2 set, add and subtract `arg3`. Other types are logged and leave the variable as it is.
+//! - A 0x02 pick stores the `arg1` of the picked option in the variable the next branch compares with a value.
+
+use crate::analysis::call_graph::script_file_name;
+use crate::analysis::variables::Variable;
+use crate::analysis::{Condition, Operand};
+use crate::opcodes::{Choice, Instruction, JumpTarget, Script, TLString};
+use std::collections::HashMap;
+
+/// How many instructions can run in a row without anything to show before the script is taken to be stuck.
+const MAX_SILENT_STEPS: usize = 1_000_000;
+
+/// What a script shows, which is where the interpreter stops.
+#[derive(Debug, Clone)]
+pub enum Event {
+	Text { speaker: Option<TLString>, text: TLString },
+	Scene { title: TLString },
+	/// Has to be answered with [`Interpreter::choose`] before going on.
+	Choice { options: Vec<Choice> },
+	/// A 0xFF, or a 0x0A with nothing to return to.
+	End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+	MissingScript { script: String, address: usize, target: String },
+	/// A jump to an address that is not an instruction of the script.
+	BadJump { script: String, address: usize },
+	/// Running past the last instruction without a 0xFF.
+	RanOffEnd { script: String },
+	NoChoiceMade,
+	/// A choice made when no [`Event::Choice`] is waiting for one.
+	NoChoicePending,
+	ChoiceOutOfRange { choice: usize, options: usize },
+	Stuck { script: String, address: usize },
+}
+
+impl std::fmt::Display for RunError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RunError::MissingScript { script, address, target } => {
+				write!(f, "{script} at 0x{address:08X} goes to {target}, which was not loaded")
+			}
+			RunError::BadJump { script, address } => write!(f, "{script} at 0x{address:08X} jumps outside of the script"),
+			RunError::RanOffEnd { script } => write!(f, "{script} ran past its last instruction"),
+			RunError::NoChoiceMade => write!(f, "a choice has to be made before going on"),
+			RunError::NoChoicePending => write!(f, "there is no choice to make"),
+			RunError::ChoiceOutOfRange { choice, options } => write!(f, "picked option {choice} of {options}"),
+			RunError::Stuck { script, address } => {
+				write!(f, "{script} ran {MAX_SILENT_STEPS} instructions without showing anything, stopped at 0x{address:08X}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for RunError {}
+
+/// The instruction that runs next, as an index into `Script::opcodes`.
+#[derive(Debug, Clone)]
+struct Position {
+	script: String,
+	index: usize,
+}
+
+pub struct Interpreter<'a> {
+	scripts: HashMap<String, &'a Script>,
+	variables: HashMap<u16, u16>,
+	position: Position,
+	/// Where each 0x07 goes back to on 0x0A.
+	calls: Vec<Position>,
+	/// The options of the choice waiting for [`Interpreter::choose`].
+	pending: Option<Vec<Choice>>,
+	/// The `arg1` of the last pick, until a branch tells which variable it went into.
+	pick: Option<u16>,
+	finished: bool,
+}
+
+impl<'a> Interpreter<'a> {
+	/// Starts at the first instruction of `start`, one of the file names of `scripts`.
+	pub fn new(scripts: impl IntoIterator<Item = (&'a str, &'a Script)>, start: &str) -> Option<Self> {
+		let scripts: HashMap<String, &Script> = scripts.into_iter().map(|(name, script)| (name.to_uppercase(), script)).collect();
+		let start = script_file_name(start);
+		if !scripts.contains_key(&start) {
+			return None;
+		}
+
+		Some(Interpreter {
+			scripts,
+			variables: HashMap::new(),
+			position: Position { script: start, index: 0 },
+			calls: vec![],
+			pending: None,
+			pick: None,
+			finished: false,
+		})
+	}
+
+	/// The value of a variable, 0 until written.
+	pub fn variable(&self, variable: u16) -> u16 {
+		self.variables.get(&variable).copied().unwrap_or_default()
+	}
+
+	/// The script and address of the instruction that runs next.
+	pub fn position(&self) -> (&str, Option<usize>) {
+		let address = self.scripts[&self.position.script].opcodes.get(self.position.index).map(|it| it.address);
+		(&self.position.script, address)
+	}
+
+	/// Answers the last [`Event::Choice`] with the option at `choice`, counting from 0.
+	pub fn choose(&mut self, choice: usize) -> Result<(), RunError> {
+		let Some(options) = &self.pending else {
+			return Err(RunError::NoChoicePending);
+		};
+		let option = options.get(choice).ok_or_else(|| RunError::ChoiceOutOfRange { choice: choice.saturating_add(1), options: options.len() })?;
+		self.pick = Some(option.arg1);
+		self.pending = None;
+		Ok(())
+	}
+
+	/// Runs until the script shows something. Keeps returning [`Event::End`] once the route has ended.
+	pub fn next_event(&mut self) -> Result<Event, RunError> {
+		if self.pending.is_some() {
+			return Err(RunError::NoChoiceMade);
+		}
+
+		for _ in 0..MAX_SILENT_STEPS {
+			if self.finished {
+				return Ok(Event::End);
+			}
+			if let Some(event) = self.step()? {
+				return Ok(event);
+			}
+		}
+
+		let (script, address) = self.position();
+		Err(RunError::Stuck { script: script.to_string(), address: address.unwrap_or_default() })
+	}
+
+	fn step(&mut self) -> Result<Option<Event>, RunError> {
+		let script = self.scripts[&self.position.script];
+		let Some(opcode) = script.opcodes.get(self.position.index) else {
+			return Err(RunError::RanOffEnd { script: self.position.script.clone() });
+		};
+		let address = opcode.address;
+		self.position.index += 1;
+
+		let Some(instruction) = Instruction::from_opcode(opcode) else {
+			log::info!("{} 0x{address:08X}: skipping unknown opcode 0x{:02X}", self.position.script, opcode.opcode);
+			return Ok(None);
+		};
+
+		match instruction {
+			Instruction::Text { text, .. } => return Ok(Some(Event::Text { speaker: None, text })),
+			Instruction::TextWithSpeaker { speaker, text, .. } => return Ok(Some(Event::Text { speaker: Some(speaker), text })),
+			Instruction::SceneTitle { title } => return Ok(Some(Event::Scene { title })),
+			Instruction::Choices { choices, .. } => {
+				self.pending = Some(choices.clone());
+				return Ok(Some(Event::Choice { options: choices }));
+			}
+			Instruction::VarOp { op_type, var, arg3, .. } => {
+				let value = self.variable(var);
+				let value = match op_type {
+					0 => arg3,
+					1 => value.wrapping_add(arg3),
+					2 => value.wrapping_sub(arg3),
+					_ => {
+						log::warn!("{} 0x{address:08X}: 0x03 type 0x{op_type:02X} is not known, leaving {} as it is", self.position.script, Variable(var));
+						value
+					}
+				};
+				self.variables.insert(var, value);
+			}
+			Instruction::Branch { branch_type, arg1, arg2, offset, .. } => {
+				let condition = Condition::from_branch(branch_type, arg1, arg2);
+				if let Operand::Value(_) = condition.operand
+					&& let Some(pick) = self.pick.take()
+				{
+					self.variables.insert(condition.variable, pick);
+				}
+				let operand = match condition.operand {
+					Operand::Value(value) => value,
+					Operand::Variable(other) => self.variable(other),
+				};
+				let holds = condition.comparison.holds(self.variable(condition.variable), operand).unwrap_or_else(|| {
+					log::warn!("{} 0x{address:08X}: {condition} has an unknown comparison, not taking it", self.position.script);
+					false
+				});
+				if holds {
+					self.jump(&offset, address)?;
+				}
+			}
+			Instruction::Jump { target, .. } => self.jump(&target, address)?,
+			Instruction::CallScript { script: target, .. } => {
+				let caller = self.position.clone();
+				self.go_to_script(&target, address)?;
+				self.calls.push(caller);
+			}
+			Instruction::GotoScript { script: target } => self.go_to_script(&target, address)?,
+			Instruction::Return { .. } => match self.calls.pop() {
+				Some(caller) => self.position = caller,
+				None => {
+					self.finished = true;
+					return Ok(Some(Event::End));
+				}
+			},
+			Instruction::End { .. } => {
+				self.finished = true;
+				return Ok(Some(Event::End));
+			}
+			other => log::info!("{} 0x{address:08X}: skipping {}", self.position.script, other.mnemonic()),
+		}
+
+		Ok(None)
+	}
+
+	fn jump(&mut self, target: &JumpTarget, address: usize) -> Result<(), RunError> {
+		let opcodes = &self.scripts[&self.position.script].opcodes;
+		let index = match target {
+			JumpTarget::Label(label) => opcodes.iter().position(|it| it.label.as_ref() == Some(label)),
+			JumpTarget::Address(target) => opcodes.iter().position(|it| it.address == *target as usize),
+		};
+		self.position.index = index.ok_or_else(|| RunError::BadJump { script: self.position.script.clone(), address })?;
+		Ok(())
+	}
+
+	fn go_to_script(&mut self, target: &TLString, address: usize) -> Result<(), RunError> {
+		let name = script_file_name(&target.raw);
+		if !self.scripts.contains_key(&name) {
+			return Err(RunError::MissingScript { script: self.position.script.clone(), address, target: name });
+		}
+		self.position = Position { script: name, index: 0 };
+		Ok(())
+	}
+}
@@ -1,4 +1,7 @@
+pub mod analysis;
 pub mod data;
+pub mod interpreter;
+pub mod lzss;
 pub mod opcodes;
 pub mod util;
 pub mod bin_utils;
@@ -19,8 +22,10 @@ macro_rules! main_preamble {
             use ccfkb_lib::logging;
 
             logging::init().unwrap();
+            ccfkb_lib::bin_utils::opcode_layouts_flag();
+            ccfkb_lib::bin_utils::variable_names_flag();
 
-            let args = std::env::args().skip(1).collect::<Vec<_>>();
+            let args = std::env::args().skip(1).filter(|it| !it.starts_with("--")).collect::<Vec<_>>();
             
             let files = args.into_iter().flat_map(|it| {
                 walkdir::WalkDir::new(it)
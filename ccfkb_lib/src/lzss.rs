@@ -0,0 +1,228 @@
+//! The LZSS variant used for WIPF image data.
+//!
+//! Every flag byte describes the next eight items, lowest bit first. A set bit is a literal byte, a clear bit is a
+//! two byte back reference `OO OL` into a 4096 byte ring: a 12 bit ring position followed by a 4 bit length, biased
+//! by 2. The ring starts out zeroed and is written from position 1.
+
+pub const RING_SIZE: usize = 4096;
+const RING_MASK: usize = RING_SIZE - 1;
+const RING_START: usize = 1;
+
+const MIN_MATCH: usize = 2;
+const MAX_MATCH: usize = 0xF + MIN_MATCH;
+
+/// How far a decode call got through its input and output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeStatus {
+	pub consumed: usize,
+	pub produced: usize,
+}
+
+/// A decoder that can be fed its input in pieces. Back references split across calls are carried over.
+pub struct Decoder {
+	ring: Box<[u8; RING_SIZE]>,
+	ring_index: usize,
+	/// The flag bits left to process, above a sentinel bit. `1` means a new flag byte is needed.
+	flags: u32,
+	/// The first byte of a back reference whose second byte is in the next piece of input.
+	half_reference: Option<u8>,
+	/// The ring position and length left of a back reference that did not fit in the output.
+	pending_copy: Option<(usize, usize)>,
+}
+
+impl Default for Decoder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Decoder {
+	pub fn new() -> Self {
+		Self {
+			ring: Box::new([0u8; RING_SIZE]),
+			ring_index: RING_START,
+			flags: 1,
+			half_reference: None,
+			pending_copy: None,
+		}
+	}
+
+	/// Decodes `input` into `output` until either runs out.
+	pub fn decode(&mut self, input: &[u8], output: &mut [u8]) -> DecodeStatus {
+		let mut in_ptr = 0usize;
+		let mut out_ptr = 0usize;
+
+		loop {
+			if let Some((mut position, mut remaining)) = self.pending_copy.take() {
+				while remaining > 0 && out_ptr < output.len() {
+					let value = self.ring[position & RING_MASK];
+					self.ring[self.ring_index & RING_MASK] = value;
+					output[out_ptr] = value;
+					position += 1;
+					self.ring_index += 1;
+					out_ptr += 1;
+					remaining -= 1;
+				}
+
+				if remaining > 0 {
+					self.pending_copy = Some((position, remaining));
+					break;
+				}
+			}
+
+			if out_ptr >= output.len() {
+				break;
+			}
+
+			if self.flags == 1 {
+				let Some(&flags) = input.get(in_ptr) else {
+					break;
+				};
+				self.flags = flags as u32 | 0x100;
+				in_ptr += 1;
+			}
+
+			if self.flags & 1 == 1 {
+				let Some(&value) = input.get(in_ptr) else {
+					break;
+				};
+				in_ptr += 1;
+				self.ring[self.ring_index & RING_MASK] = value;
+				self.ring_index += 1;
+				output[out_ptr] = value;
+				out_ptr += 1;
+			} else {
+				let high = match self.half_reference.take() {
+					Some(high) => high,
+					None => {
+						let Some(&high) = input.get(in_ptr) else {
+							break;
+						};
+						in_ptr += 1;
+						high
+					}
+				};
+
+				let Some(&low) = input.get(in_ptr) else {
+					self.half_reference = Some(high);
+					break;
+				};
+				in_ptr += 1;
+
+				let position = ((high as usize) << 4) | (low as usize >> 4);
+				let length = (low as usize & 0x0F) + MIN_MATCH;
+				self.pending_copy = Some((position, length));
+			}
+
+			self.flags >>= 1;
+		}
+
+		DecodeStatus {
+			consumed: in_ptr,
+			produced: out_ptr,
+		}
+	}
+}
+
+/// Decodes a whole stream into a buffer of `out_len` bytes. If the input runs out first, the rest of the buffer is
+/// left zeroed and the status shows how much was actually produced.
+pub fn decode(input: &[u8], out_len: usize) -> (Vec<u8>, DecodeStatus) {
+	let mut out = vec![0u8; out_len];
+	let status = Decoder::new().decode(input, &mut out);
+	(out, status)
+}
+
+const HASH_SIZE: usize = 1 << 16;
+const MAX_CHAIN: usize = 256;
+const NO_POSITION: usize = usize::MAX;
+
+fn hash_at(input: &[u8], position: usize) -> usize {
+	input[position] as usize | (input[position + 1] as usize) << 8
+}
+
+/// Compresses `input` greedily, looking for the longest match in the last 4095 bytes.
+pub fn encode(input: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(input.len() + input.len() / 8 + 1);
+	let mut head = vec![NO_POSITION; HASH_SIZE];
+	let mut prev = vec![NO_POSITION; RING_SIZE];
+
+	let mut position = 0usize;
+	let mut flag_ptr = 0usize;
+	let mut flag_bit = 8;
+
+	while position < input.len() {
+		if flag_bit == 8 {
+			flag_ptr = out.len();
+			out.push(0);
+			flag_bit = 0;
+		}
+
+		let (match_position, match_len) = find_match(input, position, &head, &prev);
+
+		if match_len >= MIN_MATCH {
+			let ring_position = (RING_START + match_position) & RING_MASK;
+			out.push((ring_position >> 4) as u8);
+			out.push((((ring_position & 0x0F) << 4) | (match_len - MIN_MATCH)) as u8);
+
+			for it in position..position + match_len {
+				insert(input, it, &mut head, &mut prev);
+			}
+			position += match_len;
+		} else {
+			out[flag_ptr] |= 1 << flag_bit;
+			out.push(input[position]);
+
+			insert(input, position, &mut head, &mut prev);
+			position += 1;
+		}
+
+		flag_bit += 1;
+	}
+
+	out
+}
+
+fn insert(input: &[u8], position: usize, head: &mut [usize], prev: &mut [usize]) {
+	if position + 1 < input.len() {
+		let hash = hash_at(input, position);
+		prev[position & RING_MASK] = head[hash];
+		head[hash] = position;
+	}
+}
+
+fn find_match(input: &[u8], position: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+	if position + 1 >= input.len() {
+		return (0, 0);
+	}
+
+	let max_len = MAX_MATCH.min(input.len() - position);
+	let (mut best_position, mut best_len) = (0, 0);
+
+	let mut candidate = head[hash_at(input, position)];
+	let mut chain = 0;
+	while candidate != NO_POSITION && position - candidate < RING_SIZE && chain < MAX_CHAIN {
+		let len = input[candidate..]
+			.iter()
+			.zip(&input[position..position + max_len])
+			.take_while(|(a, b)| a == b)
+			.count();
+
+		if len > best_len {
+			best_position = candidate;
+			best_len = len;
+			if len == max_len {
+				break;
+			}
+		}
+
+		let next = prev[candidate & RING_MASK];
+		// The slot may have been reused by a later position, which ends this chain.
+		if next == NO_POSITION || next >= candidate {
+			break;
+		}
+		candidate = next;
+		chain += 1;
+	}
+
+	(best_position, best_len)
+}
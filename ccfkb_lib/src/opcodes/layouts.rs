@@ -0,0 +1,161 @@
+//! Opcode layouts loaded from a YAML file at runtime, to try out layout hypotheses without rebuilding.
+//!
+//! The file is a list of layouts using the same field vocabulary as the built-in table. A field is either a bare
+//! kind or `name:kind`; unnamed fields are called `argN`, or `padN` for padding.
+//!
+//! ```yaml
+//! - opcode: 0x41
+//!   mnemonic: text
+//!   doc: Writes textbox content, without a speaker.
+//!   fields: arg1:w b b text:s
+//! ```
+//!
+//! Layouts in the file replace the built-in ones for their opcode, every other opcode keeps its built-in layout.
+
+use crate::data::fix_yaml_str;
+use crate::opcodes::{FieldKind, FieldSpec, OpcodeSpec, OPCODE_SPECS};
+use once_cell::sync::OnceCell;
+use serde_derive::{Deserialize, Serialize};
+
+static LAYOUT_OVERRIDES: OnceCell<Vec<OpcodeSpec>> = OnceCell::new();
+
+#[derive(Serialize, Deserialize)]
+struct LayoutEntry {
+	#[serde(serialize_with = "crate::opcodes::serialize_hex_u8")]
+	opcode: u8,
+	#[serde(default)]
+	mnemonic: Option<String>,
+	#[serde(default)]
+	doc: Option<String>,
+	#[serde(default)]
+	fields: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+	Yaml(String),
+	UnknownKind { opcode: u8, field: String },
+	DuplicateOpcode(u8),
+	/// A choice field needs an earlier byte field holding the number of choices.
+	ChoicesWithoutCount(u8),
+	AlreadyLoaded,
+}
+
+impl std::fmt::Display for LayoutError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LayoutError::Yaml(err) => write!(f, "bad layout file: {err}"),
+			LayoutError::UnknownKind { opcode, field } => {
+				write!(f, "opcode 0x{opcode:02X} has field {field:?}, expected one of b, w, d, s, c, p, j or r")
+			}
+			LayoutError::DuplicateOpcode(opcode) => write!(f, "opcode 0x{opcode:02X} is defined more than once"),
+			LayoutError::ChoicesWithoutCount(opcode) => {
+				write!(f, "opcode 0x{opcode:02X} has a choice field without a byte field before it to count the choices")
+			}
+			LayoutError::AlreadyLoaded => write!(f, "opcode layouts have already been loaded"),
+		}
+	}
+}
+
+impl std::error::Error for LayoutError {}
+
+/// The layout loaded for `opcode`, if any.
+pub(crate) fn layout_override(opcode: u8) -> Option<&'static OpcodeSpec> {
+	LAYOUT_OVERRIDES.get()?.iter().find(|it| it.opcode == opcode)
+}
+
+fn leak(text: String) -> &'static str {
+	Box::leak(text.into_boxed_str())
+}
+
+/// Parses a layout file. The layouts live for the rest of the program, as the built-in ones do.
+pub fn parse_opcode_layouts(text: &str) -> Result<Vec<OpcodeSpec>, LayoutError> {
+	let entries: Vec<LayoutEntry> = serde_yml::from_str(text).map_err(|err| LayoutError::Yaml(err.to_string()))?;
+
+	let mut specs: Vec<OpcodeSpec> = vec![];
+	for entry in entries {
+		if specs.iter().any(|it| it.opcode == entry.opcode) {
+			return Err(LayoutError::DuplicateOpcode(entry.opcode));
+		}
+
+		let mut fields = vec![];
+		let (mut n_args, mut n_pads) = (0, 0);
+		for token in entry.fields.split_whitespace() {
+			let (name, code) = token.rsplit_once(':').map(|(name, code)| (Some(name), code)).unwrap_or((None, token));
+			let kind = FieldKind::from_code(code)
+				.ok_or_else(|| LayoutError::UnknownKind { opcode: entry.opcode, field: token.to_string() })?;
+
+			if kind == FieldKind::Choices && !fields.iter().any(|it: &FieldSpec| it.kind == FieldKind::Byte) {
+				return Err(LayoutError::ChoicesWithoutCount(entry.opcode));
+			}
+
+			let name = match (name, kind) {
+				(Some(name), _) => name.to_string(),
+				(None, FieldKind::Padding) => {
+					n_pads += 1;
+					if n_pads == 1 { "pad".to_string() } else { format!("pad{n_pads}") }
+				}
+				(None, _) => {
+					n_args += 1;
+					format!("arg{n_args}")
+				}
+			};
+			fields.push(FieldSpec { name: leak(name), kind });
+		}
+
+		let builtin = OPCODE_SPECS.iter().find(|it| it.opcode == entry.opcode);
+		specs.push(OpcodeSpec {
+			opcode: entry.opcode,
+			mnemonic: match (entry.mnemonic, builtin) {
+				(Some(mnemonic), _) => leak(mnemonic),
+				(None, Some(builtin)) => builtin.mnemonic,
+				(None, None) => leak(format!("op_{:02x}", entry.opcode)),
+			},
+			doc: match (entry.doc, builtin) {
+				(Some(doc), _) => leak(doc),
+				(None, Some(builtin)) => builtin.doc,
+				(None, None) => "",
+			},
+			fields: Box::leak(fields.into_boxed_slice()),
+		});
+	}
+
+	Ok(specs)
+}
+
+/// Makes `make_opcode` use `specs` in place of the built-in layouts of their opcodes. Can only be done once, before
+/// any script is decoded.
+pub fn set_opcode_layouts(specs: Vec<OpcodeSpec>) -> Result<(), LayoutError> {
+	for spec in specs.iter() {
+		let builtin = OPCODE_SPECS.iter().find(|it| it.opcode == spec.opcode);
+		if builtin.is_some_and(|builtin| layout_string(builtin) == layout_string(spec)) {
+			continue;
+		}
+
+		log::info!("Using layout \"{}\" for opcode 0x{:02X}", layout_string(spec), spec.opcode);
+		if builtin.is_some_and(|builtin| !builtin.fields.iter().map(|it| it.kind).eq(spec.fields.iter().map(|it| it.kind))) {
+			log::warn!("Opcode 0x{:02X} no longer matches its built-in fields, so it is skipped when extracting text and relocating jumps.", spec.opcode);
+		}
+	}
+
+	LAYOUT_OVERRIDES.set(specs).map_err(|_| LayoutError::AlreadyLoaded)
+}
+
+fn layout_string(spec: &OpcodeSpec) -> String {
+	spec.fields.iter().map(|it| format!("{}:{}", it.name, it.kind.code())).collect::<Vec<_>>().join(" ")
+}
+
+/// Writes layouts in the format read by [`parse_opcode_layouts`], as a starting point for a layout file.
+pub fn write_opcode_layouts<'a>(specs: impl IntoIterator<Item = &'a OpcodeSpec>) -> String {
+	let entries: Vec<_> = specs
+		.into_iter()
+		.map(|spec| LayoutEntry {
+			opcode: spec.opcode,
+			mnemonic: Some(spec.mnemonic.to_string()),
+			doc: Some(spec.doc.to_string()),
+			fields: layout_string(spec),
+		})
+		.collect();
+
+	fix_yaml_str(serde_yml::to_string(&entries).unwrap())
+}
@@ -1,5 +1,10 @@
-use crate::util::{encode_sjis, get_sjis_bytes, transmute_to_u16};
-use itertools::Itertools;
+mod layouts;
+mod spec;
+
+pub use layouts::{parse_opcode_layouts, set_opcode_layouts, write_opcode_layouts, LayoutError};
+pub use spec::{opcode_spec, opcode_specs, FieldKind, FieldSpec, Instruction, OpcodeSpec, OPCODE_SPECS};
+
+use crate::util::{encode_sjis, get_sjis_bytes, transmute_to_u16, transmute_to_u32};
 use serde::Serializer;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -40,6 +45,12 @@ pub enum OpField {
 	String(TLString),
 	Choice(Vec<Choice>),
 	Padding(u8),
+	/// The undecoded bytes of an unknown opcode, kept as they are.
+	Raw(
+		#[serde(serialize_with = "crate::opcodes::serialize_inline_ints_vec")]
+		Vec<u8>),
+	/// A jump target, written as the address of the instruction with this label.
+	Label(String),
 }
 
 impl OpField {
@@ -49,19 +60,6 @@ impl OpField {
 			_ => None,
 		}
 	}
-	fn as_word(&self) -> Option<u16> {
-		match &self {
-			OpField::Word(w) => Some(*w),
-			_ => None,
-		}
-	}
-
-	fn as_dword(&self) -> Option<u32> {
-		match &self {
-			OpField::DWord(d) => Some(*d),
-			_ => None,
-		}
-	}
 
 	fn size(&self) -> usize {
 		match self {
@@ -76,7 +74,9 @@ impl OpField {
 				}
 				acc
 			}
-			OpField::Padding(size) => *size as usize
+			OpField::Padding(size) => *size as usize,
+			OpField::Raw(bytes) => bytes.len(),
+			OpField::Label(_) => 4,
 		}
 	}
 
@@ -94,13 +94,15 @@ impl OpField {
 				}
 			}
 			OpField::Padding(number) => buf.extend(vec![0; *number as usize]),
+			OpField::Raw(bytes) => buf.extend(bytes),
+			OpField::Label(_) => unreachable!("labels are resolved by Opcode::encode"),
 		};
 
 		buf
 	}
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Choice {
 	#[serde(serialize_with = "crate::opcodes::serialize_hex_u16")]
 	pub arg1: u16,
@@ -110,6 +112,13 @@ pub struct Choice {
 }
 
 impl Choice {
+	/// `arg4` of the trailer, possibly the address the option jumps to. 0 is taken as no target, as the first
+	/// instruction of a script is not one a choice goes back to.
+	pub fn target(&self) -> Option<usize> {
+		let arg4 = u16::from_le_bytes(self.trailer.get(1..3)?.try_into().ok()?);
+		(arg4 != 0).then_some(arg4 as usize)
+	}
+
 	fn size(&self) -> usize {
 		let str_len = if let Some(tl) = &self.choice_str.translation {
 			encode_sjis(tl).len() + 1
@@ -136,10 +145,37 @@ pub struct Opcode {
 	pub address: usize,
 	#[serde(skip)]
 	pub actual_address: usize,
+	/// The label jumps use to refer to this instruction.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub label: Option<String>,
 	pub fields: Vec<OpField>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Where a jump goes: the label of an instruction in the same script, or an address no instruction starts at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JumpTarget {
+	Label(String),
+	Address(u32),
+}
+
+impl JumpTarget {
+	pub(crate) fn from_field(field: &OpField) -> Option<Self> {
+		match field {
+			OpField::Label(label) => Some(JumpTarget::Label(label.clone())),
+			OpField::DWord(address) => Some(JumpTarget::Address(*address)),
+			_ => None,
+		}
+	}
+
+	pub(crate) fn to_field(&self) -> OpField {
+		match self {
+			JumpTarget::Label(label) => OpField::Label(label.clone()),
+			JumpTarget::Address(address) => OpField::DWord(*address),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Script {
 	pub opcodes: Vec<Opcode>,
 	#[serde(serialize_with = "crate::opcodes::serialize_inline_ints_vec")]
@@ -147,104 +183,192 @@ pub struct Script {
 }
 
 impl Script {
-	pub fn binary_serialise(mut self) -> Vec<u8> {
-		let mut buf = vec![];
-
-		let mut jump_map: HashMap<u32, usize> = HashMap::new();
+	/// Serialises the script, placing every instruction after the one before it and pointing labelled jumps at the
+	/// new address of their label.
+	pub fn binary_serialise(&self) -> Result<Vec<u8>, EncodeError> {
+		let mut addresses = vec![];
+		let mut labels: HashMap<&str, usize> = HashMap::new();
 		let mut actual_address = self
 			.opcodes
 			.first()
 			.map(|it| it.address)
 			.unwrap_or_default();
 
-		let orig_opcodes = self.opcodes.clone();
-
 		log::debug!("Actual address start is 0x{actual_address:08X}");
-		for opcode in self.opcodes.iter_mut() {
-			match opcode.opcode {
-				0x06 => {
-					let (idx, orig_op) = orig_opcodes
-						.iter().find_position(|it| it.address == (opcode.fields[0].as_dword().unwrap() as usize))
-						.unwrap();
-					log::debug!(
-            "Direct jump opcode at 0x{:08X} (actual 0x{:08X}) jumps to: 0x{:04X}",
-            opcode.address,
-            actual_address,
-            orig_op.address,
-          );
-					jump_map.insert(opcode.address as u32, idx);
-				}
-				0x01 => {
-					let (idx, orig_op) = orig_opcodes.iter().find_position(|it| it.address == (opcode.address + 11 + opcode.fields[3].as_dword().unwrap() as usize))
-						.unwrap();
-
-					jump_map.insert(opcode.address as u32, idx);
-					log::debug!(
-            "Conditional jump Opcode at 0x{:08X} (actual {:08X}) jumps to: {:08X}",
-            opcode.address,
-            actual_address,
-            orig_op.address
-          );
-				}
-				_ => {}
+		for opcode in &self.opcodes {
+			if let Some(label) = &opcode.label
+				&& labels.insert(label, actual_address).is_some()
+			{
+				return Err(EncodeError::DuplicateLabel(label.clone()));
 			}
-			opcode.actual_address = actual_address;
+			addresses.push(actual_address);
 			actual_address += opcode.size();
 		}
 
-
-		for op in &self.opcodes {
-			let op = adjust_single_opcode(op.clone(), &jump_map, &self.opcodes);
-			let serialised = op.binary_serialise();
-			buf.extend(serialised);
+		let mut buf = vec![];
+		for (opcode, address) in self.opcodes.iter().zip(addresses) {
+			buf.extend(opcode.encode(address, &labels)?);
 		}
 
 		buf.extend(&self.trailer);
 
-		buf
+		Ok(buf)
+	}
+
+	/// Turns jump targets held as addresses into labels on the instructions they point at. Targets no instruction
+	/// starts at are left as addresses, so they still serialise to the same bytes but no longer follow edits.
+	pub fn label_jumps(&mut self) {
+		// Inserted instructions share the address of the one before them, so the first one with an address wins.
+		let mut index: HashMap<usize, usize> = HashMap::new();
+		for (idx, opcode) in self.opcodes.iter().enumerate() {
+			index.entry(opcode.address).or_insert(idx);
+		}
+
+		let mut jumps = vec![];
+		for (idx, opcode) in self.opcodes.iter().enumerate() {
+			let Some(spec) = opcode_spec(opcode.opcode) else {
+				continue;
+			};
+			for (field_idx, field) in spec.fields.iter().enumerate() {
+				let Some(OpField::DWord(value)) = opcode.fields.get(field_idx) else {
+					continue;
+				};
+				let target = match field.kind {
+					FieldKind::Jump => *value as usize,
+					FieldKind::RelativeJump => ((opcode.address + opcode.size()) as u32).wrapping_add(*value) as usize,
+					_ => continue,
+				};
+				jumps.push((idx, field_idx, target));
+			}
+		}
+
+		for (idx, field_idx, target) in jumps {
+			let Some(&target_idx) = index.get(&target) else {
+				log::warn!(
+          "Jump at 0x{:08X} targets 0x{target:08X}, where no instruction starts",
+          self.opcodes[idx].address
+        );
+				continue;
+			};
+
+			let label = self.opcodes[target_idx].label.get_or_insert_with(|| label_name(target)).clone();
+			log::debug!("Jump at 0x{:08X} targets {label}", self.opcodes[idx].address);
+			self.opcodes[idx].fields[field_idx] = OpField::Label(label);
+		}
 	}
+
+	/// Inserts `opcode` before the instruction at `index`. It takes the address of the instruction before it, as it
+	/// has none of its own in the original script, so tools matching instructions by address see it as part of that
+	/// instruction.
+	pub fn insert(&mut self, index: usize, mut opcode: Opcode) -> Result<(), EditError> {
+		if index > self.opcodes.len() {
+			return Err(EditError::OutOfRange { index, len: self.opcodes.len() });
+		}
+		if let Some(label) = &opcode.label
+			&& self.opcodes.iter().any(|it| it.label.as_ref() == Some(label))
+		{
+			return Err(EditError::DuplicateLabel(label.clone()));
+		}
+
+		self.label_jumps();
+
+		let neighbour = index.checked_sub(1).or((!self.opcodes.is_empty()).then_some(0));
+		opcode.address = neighbour.map(|it| self.opcodes[it].address).unwrap_or_default();
+		opcode.actual_address = opcode.address;
+		self.opcodes.insert(index, opcode);
+
+		Ok(())
+	}
+
+	/// Removes the instruction at `index`. Jumps to it go to the instruction after it instead, which is where
+	/// execution would have continued.
+	pub fn delete(&mut self, index: usize) -> Result<Opcode, EditError> {
+		if index >= self.opcodes.len() {
+			return Err(EditError::OutOfRange { index, len: self.opcodes.len() });
+		}
+		if let Some(label) = &self.opcodes[index].label
+			&& index + 1 == self.opcodes.len()
+		{
+			return Err(EditError::JumpTargetAtEnd(label.clone()));
+		}
+
+		self.label_jumps();
+
+		let removed = self.opcodes.remove(index);
+		let Some(label) = &removed.label else {
+			return Ok(removed);
+		};
+
+		let next = &mut self.opcodes[index];
+		match next.label.clone() {
+			None => next.label = Some(label.clone()),
+			Some(next_label) => {
+				for field in self.opcodes.iter_mut().flat_map(|it| it.fields.iter_mut()) {
+					if matches!(field, OpField::Label(it) if it == label) {
+						*field = OpField::Label(next_label.clone());
+					}
+				}
+			}
+		}
+		log::debug!("Jumps to deleted instruction {label} moved to the one after it");
+
+		Ok(removed)
+	}
+}
+
+/// Why [`Script::insert`] or [`Script::delete`] refused an edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+	OutOfRange { index: usize, len: usize },
+	DuplicateLabel(String),
+	/// The last instruction is a jump target, so jumps to it would have nowhere to go once it is deleted.
+	JumpTargetAtEnd(String),
 }
 
-fn adjust_single_opcode(
-	opcode: Opcode,
-	jump_table: &HashMap<u32, usize>,
-	opcodes: &[Opcode],
-) -> Opcode {
-	let mut opcode = opcode;
-	match opcode.opcode {
-		0x06 => {
-			let tbl_entry = jump_table[&(opcode.address as u32)];
-			opcode.fields[0] = OpField::DWord(opcodes[tbl_entry].actual_address as u32);
-			log::debug!(
-        "Adjusting direct jump Opcode at 0x{:08X} (actual {:08X}) to jump to: {:08X}",
-        opcode.address,
-        opcode.actual_address,
-        opcode.fields[0].as_word().unwrap(),
-      );
-			opcode
+impl std::fmt::Display for EditError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			EditError::OutOfRange { index, len } => write!(f, "index {index} is out of range for {len} instructions"),
+			EditError::DuplicateLabel(label) => write!(f, "label {label} is already on another instruction"),
+			EditError::JumpTargetAtEnd(label) => {
+				write!(f, "the last instruction is jumped to as {label}, there is no instruction after it to jump to instead")
+			}
 		}
-		// conditional jump
-		0x01 => {
-			let tbl_entry = jump_table[&(opcode.address as u32)];
-			let curr_actual_address = opcode.actual_address;
-			let target_address = opcodes[tbl_entry].actual_address;
-			let offset = target_address - (curr_actual_address + 11);
-			opcode.fields[3] = OpField::DWord(offset as u32);
-			log::debug!(
-        "Adjusting conditional jump Opcode ({:02X}) at 0x{:08X} (actual 0x{:08X}) originally targetting {:08X} to jump to offset: 0x{:04X} (0x{:08X})",
-        opcode.opcode,
-        opcode.address,
-        opcode.actual_address,
-        opcodes[tbl_entry].address,
-        offset,
-        target_address
-      );
-			opcode
+	}
+}
+
+impl std::error::Error for EditError {}
+
+/// The label [`Script::label_jumps`] gives the instruction at `address`.
+pub fn label_name(address: usize) -> String {
+	format!("L_0x{address:08X}")
+}
+
+/// Why a script could not be serialised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+	/// A jump at `address` names a label no instruction has.
+	UnknownLabel { address: usize, label: String },
+	DuplicateLabel(String),
+	/// A label in a field that the layout of the opcode does not have as a jump.
+	NotAJump { address: usize, opcode: u8 },
+}
+
+impl std::fmt::Display for EncodeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			EncodeError::UnknownLabel { address, label } => {
+				write!(f, "jump at 0x{address:08X} targets {label}, which no instruction has")
+			}
+			EncodeError::DuplicateLabel(label) => write!(f, "label {label} is on more than one instruction"),
+			EncodeError::NotAJump { address, opcode } => {
+				write!(f, "opcode 0x{opcode:02X} at 0x{address:08X} has a label in a field that is not a jump")
+			}
 		}
-		_ => opcode,
 	}
 }
 
+impl std::error::Error for EncodeError {}
 
 impl Opcode {
 	pub(crate) fn size(&self) -> usize {
@@ -255,14 +379,35 @@ impl Opcode {
 		acc
 	}
 
-	pub(crate) fn binary_serialise(&self) -> Vec<u8> {
+	/// Serialises a single opcode, which cannot refer to labels as there is no script to find them in.
+	pub(crate) fn binary_serialise(&self) -> Result<Vec<u8>, EncodeError> {
+		self.encode(self.address, &HashMap::new())
+	}
+
+	/// Serialises the opcode as if it were at `address`, writing labels as jumps to their address in `labels`.
+	fn encode(&self, address: usize, labels: &HashMap<&str, usize>) -> Result<Vec<u8>, EncodeError> {
 		let mut buf = vec![self.opcode];
 
-		for field in &self.fields {
-			buf.extend(field.binary_serialise());
+		let spec = opcode_spec(self.opcode);
+		for (idx, field) in self.fields.iter().enumerate() {
+			let OpField::Label(label) = field else {
+				buf.extend(field.binary_serialise());
+				continue;
+			};
+
+			let target = *labels
+				.get(label.as_str())
+				.ok_or_else(|| EncodeError::UnknownLabel { address: self.address, label: label.clone() })?;
+			let value = match spec.and_then(|it| it.fields.get(idx)).map(|it| it.kind) {
+				Some(FieldKind::Jump) => target as u32,
+				Some(FieldKind::RelativeJump) => (target as u32).wrapping_sub((address + self.size()) as u32),
+				_ => return Err(EncodeError::NotAJump { address: self.address, opcode: self.opcode }),
+			};
+			log::debug!("Jump at 0x{address:08X} to {label} written as 0x{value:08X}");
+			buf.extend(value.to_le_bytes());
 		}
 
-		buf
+		Ok(buf)
 	}
 }
 
@@ -337,262 +482,106 @@ where
 	serializer.serialize_str(&string)
 }
 
-fn make_choice(input: &[u8]) -> Choice {
+/// Why the bytes at some address could not be read as an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpcodeError {
+	/// There is no layout for this opcode byte.
+	Unknown(u8),
+	/// The layout of this opcode runs past the end of the input.
+	Truncated(u8),
+	Empty,
+}
+
+impl std::fmt::Display for OpcodeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			OpcodeError::Unknown(opcode) => write!(f, "unknown opcode 0x{opcode:02X}"),
+			OpcodeError::Truncated(opcode) => write!(f, "opcode 0x{opcode:02X} runs past the end of the script"),
+			OpcodeError::Empty => write!(f, "no input left"),
+		}
+	}
+}
+
+impl std::error::Error for OpcodeError {}
+
+/// Reads a NUL terminated string at `ptr`, if one is terminated before the end of `input`.
+fn read_string(ptr: usize, input: &[u8]) -> Option<(usize, TLString)> {
+	if !input.get(ptr..)?.contains(&0) {
+		return None;
+	}
+
+	let (bytes, raw) = get_sjis_bytes(ptr, input);
+	Some((bytes.len(), TLString { raw, translation: None, notes: None }))
+}
+
+fn make_choice(input: &[u8]) -> Option<Choice> {
 	let mut ptr = 0;
 
-	let arg1 = transmute_to_u16(ptr, input);
+	let arg1 = transmute_to_u16(ptr, input.get(..2)?);
 	ptr += 2;
 
-	let (bytes, choice_str) = get_sjis_bytes(ptr, input);
-	ptr += bytes.len();
+	let (len, choice_str) = read_string(ptr, input)?;
+	ptr += len;
 
-	let trailer = &input[ptr..(ptr + 11)];
+	let trailer = input.get(ptr..(ptr + 11))?;
 
-	Choice {
+	Some(Choice {
 		arg1,
-		choice_str: TLString {
-			raw: choice_str,
-			translation: None,
-			notes: None,
-		},
+		choice_str,
 		trailer: trailer.to_vec(),
-	}
+	})
 }
 
+/// Decodes the opcode at the start of `input`, logging why if it cannot.
 pub fn make_opcode(input: &[u8], addr: usize) -> Option<Opcode> {
+	read_opcode(input, addr).inspect_err(|err| log::error!("At 0x{addr:08X}: {err}")).ok()
+}
+
+/// Decodes the opcode at the start of `input` following its layout, without reading past the end of `input`.
+pub fn read_opcode(input: &[u8], addr: usize) -> Result<Opcode, OpcodeError> {
+	let opcode = *input.first().ok_or(OpcodeError::Empty)?;
+	let spec = opcode_spec(opcode).ok_or(OpcodeError::Unknown(opcode))?;
+	let truncated = OpcodeError::Truncated(opcode);
+
 	let mut ptr = 1usize;
 	let mut fields = vec![];
-
-	macro_rules! expand_opcode_component {
-        (c) => {
-            {
-                let n_choices = match &fields[0] {
-                    OpField::Byte(n) => *n,
-                    _ => panic!("Weird shit!")
-                };
-                let mut choices = vec![];
-                let mut curr_ptr = ptr;
-                for _ in 0..n_choices {
-                  let choice = make_choice(&input[curr_ptr..]);
-                  curr_ptr += choice.size();
-                  choices.push(choice);
-                }
-                ptr += choices.iter().map(|it| it.size()).sum::<usize>();
-                fields.push(OpField::Choice(choices));
-            }
-        };
-        (s) => {
-            {
-                let (bytes, string) = crate::util::get_sjis_bytes(ptr, input);
-                fields.push(OpField::String(TLString {
-                    raw: string,
-                    translation: None,
-                    notes: None,
-                }));
-                ptr += bytes.len();
-            }
-        };
-        (b) => {
-            {
-                fields.push(OpField::Byte(input[ptr]));
-                ptr += 1;
-            }
-        };
-        (w) => {
-            {
-                fields.push(OpField::Word(crate::util::transmute_to_u16(ptr, input)));
-                ptr += 2;
-            }
-        };
-        (d) => {
-            {
-                fields.push(OpField::DWord(crate::util::transmute_to_u32(ptr, input)));
-                ptr += 4;
-            }
-        };
-        (p) => {
-            {
-                fields.push(OpField::Padding(1));
-                ptr += 1;
-            }
-        };
-    }
-
-	macro_rules! expand_opcode {
-        () => {
-            {
-
-            }
-        };
-        (c, $($tail:tt)*) => {
-                expand_opcode_component!(c);
-
-                expand_opcode!($($tail)*)
-        };
-        (s, $($tail:tt)*) => {
-                expand_opcode_component!(s);
-
-                expand_opcode!($($tail)*)
-        };
-        (b, $($tail:tt)*) => {
-                expand_opcode_component!(b);
-
-                expand_opcode!($($tail)*)
-        };
-        (w, $($tail:tt)*) => {
-                expand_opcode_component!(w);
-
-                expand_opcode!($($tail)*)
-        };
-        (d, $($tail:tt)*) => {
-                expand_opcode_component!(d);
-
-                expand_opcode!($($tail)*)
-        };
-        (p, $($tail:tt)*) => {
-                expand_opcode_component!(p);
-
-                expand_opcode!($($tail)*)
-        };
-    }
-
-	match &input[0] {
-		0x01 => {
-			expand_opcode!(b, w, w, d, p,);
-		} // n_byte_opcode(input, 11), // : 11 bytes (1 + 1 + 2 + 2 + 4 + 1)
-		0x02 => { expand_opcode!(b, p, c,); } // 0x02: Variable (minimum 4 bytes + choice data)
-		0x03 => { expand_opcode!(b, w, b, w, p,); } // n_byte_opcode(input, 8), // : 8 bytes (1 + 1 + 2 + 1 + 2 + 1)
-		0x04 => expand_opcode!(), // n_byte_opcode(input, 1), // 0x04: 1 byte
-		0x05 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x06 => { expand_opcode!(d, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 2)
-		0x07 => { expand_opcode!(w, s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0x08 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes
-		0x09 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0x0A => { expand_opcode!(p,); } // n_byte_opcode(input, 1), //: 1 byte
-		0x0B => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x0C => { expand_opcode!(w, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 2 + 1)
-		0x0D => { expand_opcode!(w, w, w, p,); } // n_byte_opcode(input, 10), //: 10 bytes (1 + 2 + 2 + 4 + 1)
-		0x0E => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x21 => { expand_opcode!(b, w, b, w, d, s,); } // make_string_opcode(12, input), //: Variable (minimum 13 bytes + string length)
-		0x22 => { expand_opcode!(b, w, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 2 + 1)
-		0x23 | 0x27 => { expand_opcode!(b, w, w, w, b, b, s,); } // make_string_opcode(10, input), // 0x27: Variable (minimum 11 bytes + string length)
-		0x24 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x25 => { expand_opcode!(b, b, w, p, p, b, w, b, s,); } // make_string_opcode(12, input), //: Variable (minimum 13 bytes + string length)
-		0x26 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x28 => { expand_opcode!(b, b, p, p, p,); } // n_byte_opcode(input, 6), //: 6 bytes (1 + 1 + 1 + 3)
-		0x29 => { expand_opcode!(b, w, p, p,); } // n_byte_opcode(input, 6), //: 6 bytes (1 + 1 + 2 + 2)
-		0x30 => { expand_opcode!(b, p, p, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 3)
-		0x31 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x32 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x33 => { expand_opcode!(w, w, w, p,); } // n_byte_opcode(input, 8), //: 8 bytes (1 + 2 + 2 + 2 + 1)
-		0x34 => { expand_opcode!(w, b, b, s,); } // make_string_opcode(6, input), //: Variable (minimum 7 bytes + string length)
-		0x41 => { expand_opcode!(w, b, b, s,); } // make_string_opcode(5, input), //: Variable (minimum 6 bytes + string length)
-		0x42 => { expand_opcode!(w, b, b, b, s, s,); } // make_n_string_opcode(6, 2, input), //: Variable (minimum 7 bytes + 2 string lengths)
-		0x43 => { expand_opcode!(b, w, w, b, s,); } // make_string_opcode(7, input), //: Variable (minimum 8 bytes + string length)
-		0x44 => { expand_opcode!(b, b, b, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 1 + 1 + 1)
-		0x45 => { expand_opcode!(b, b, b, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 1 + 1 + 1)
-		0x46 => { expand_opcode!(w, w, p, p, p, b, b, s,); } // make_string_opcode(10, input), //: Variable (minimum 11 bytes + string length)
-		0x47 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x48 => { expand_opcode!(b, w, w, d, b, b, s,); } // n_byte_opcode(input, 13), //: 13 bytes (1 + 11 + 1)
-		0x49 => { expand_opcode!(w, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 2 + 1)
-		0x4A => { expand_opcode!(b, w, w, p,); } // n_byte_opcode(input, 8), //: 8 bytes (1 + 1 + 2 + 2 + 1)
-		0x4B => { expand_opcode!(b, w, w, d, w, d, d, p,); } // n_byte_opcode(input, 24), //: 24 bytes (1 + 1 + 2 + 2 + 4 + 2 + 4 + 4 + 1)
-		0x4C => { expand_opcode!(b, b, d, p, p,); } // n_byte_opcode(input, 9), //: 9 bytes (1 + 1 + 1 + 4 + 1 + 1)
-		0x4D => { expand_opcode!(b, b, w, w, w, w, w, p,); } // n_byte_opcode(input, 15), //: 15 bytes (1 + 1 + 1 + 2 + 2 + 2 + 2 + 2 + 1)
-		0x4E => { expand_opcode!(b, b, b, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 1 + 1 + 1)
-		0x4F => { expand_opcode!(b, b, b, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 1 + 1 + 1)
-		0x50 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0x51 => { expand_opcode!(w, w, p,); } // n_byte_opcode(input, 6), //: 6 bytes (1 + 2 + 2 + 1)
-		0x52 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x53 => { expand_opcode!(b, w, w, s,); } // make_string_opcode(6, input), //: Variable (minimum 7 bytes + string length)
-		0x54 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0x55 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x56 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x57 => { expand_opcode!(w, w, d, p,); } // n_byte_opcode(input, 10), //: 10 bytes (1 + 2 + 2 + 4 + 1)
-		0x58 => { expand_opcode!(b, b, b, w, w, p,); } // n_byte_opcode(input, 10), //: 10 bytes (1 + 1 + 1 + 1 + 2 + 2 + 1)
-		0x59 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0x60 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x61 => { expand_opcode!(b, s,); } // make_string_opcode(2, input), //: Variable (minimum 3 bytes + string length)
-		0x62 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x63 => { expand_opcode!(b, b, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 1 + 1 + 1)
-		0x64 => { expand_opcode!(b, w, w, w, p,); } // n_byte_opcode(input, 9), //: 9 bytes (1 + 1 + 2 + 2 + 2 + 1)
-		0x65 => { expand_opcode!(w, w, p,); } // n_byte_opcode(input, 6), //: 6 bytes (1 + 2 + 2 + 1)
-		0x66 => { expand_opcode!(b, w, w, b, w, d, w, d, d,); } // n_byte_opcode(input, 24), //: 24 bytes (1 + 1 + 2 + 2 + 1 + 2 + 4 + 2 + 4 + 4)
-		0x67 => { expand_opcode!(b, b, p, d, p,); } // n_byte_opcode(input, 9), //: 9 bytes (1 + 1 + 1 + 1 + 4 + 1)
-		0x68 => { expand_opcode!(w, w, w, w, p,); } // n_byte_opcode(input, 10), //: 10 bytes (1 + 2 + 2 + 2 + 2 + 1)
-		0x69 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x70 => { expand_opcode!(b, b, p, d, p,); } // n_byte_opcode(input, 9), //: 9 bytes (1 + 1 + 1 + 1 + 4 + 1)
-		0x71 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0x72 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x73 => { expand_opcode!(w, w, d, b, s,); } // make_string_opcode(9, input), //: Variable (minimum 10 bytes + string length)
-		0x74 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x75 => { expand_opcode!(w, w, w, w, p,); } // n_byte_opcode(input, 10), //: 10 bytes (1 + 2 + 2 + 2 + 2 + 1)
-		0x76 => { expand_opcode!(w, w, d, b, b, w, d, p,); } // n_byte_opcode(input, 18), //: 18 bytes (1 + 2 + 2 + 4 + 1 + 1 + 2 + 4 + 1)
-		0x77 => { expand_opcode!(w, w, d, p,); } // n_byte_opcode(input, 10), //: 10 bytes (1 + 2 + 2 + 4 + 1)
-		0x78 => { expand_opcode!(b, b, b, d, p,); } // n_byte_opcode(input, 9), //: 9 bytes (1 + 1 + 1 + 1 + 4 + 1)
-		0x79 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x81 => { expand_opcode!(p, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x82 => { expand_opcode!(w, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 2 + 1)
-		0x83 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x84 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x85 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x86 => { expand_opcode!(p, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0x87 => { expand_opcode!(w, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 2 + 1)
-		0x88 => { expand_opcode!(p, p, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 1 + 1 + 1)
-		0x89 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x8A => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x8B => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x8C => { expand_opcode!(w, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 2 + 1)
-		0x8D => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0x8E => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xA0 => { expand_opcode!(w, w, b, p,); } // n_byte_opcode(input, 7), //: 7 bytes (1 + 2 + 2 + 1 + 1)
-		0xA1 => { expand_opcode!(b, w, w, b, p,); } // n_byte_opcode(input, 9), //: 9 bytes (1 + 1 + 2 + 2 + 1 + 1)
-		0xA2 => { expand_opcode!(b, w, w, p,); } // n_byte_opcode(input, 8), //: 8 bytes (1 + 1 + 2 + 2 + 1)
-		0xA3 => { expand_opcode!(p, w, w, p,); } // n_byte_opcode(input, 7), //: 7 bytes (1 + 1 + 2 + 2 + 1)
-		0xA4 => { expand_opcode!(p, w, w, p,); } // n_byte_opcode(input, 7), //: 7 bytes (1 + 1 + 2 + 2 + 1)
-		0xA5 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0xA6 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xA7 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xA8 => { expand_opcode!(b, b, b, p, p, p, p, w, w, w, w, p,); } // n_byte_opcode(input, 19), //: 19 bytes (1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 2 + 2 + 2 + 2 + 1)
-		0xA9 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xAA => { expand_opcode!(b, b, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 1 + 1 + 1)
-		0xAB => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xAC => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xAD => { expand_opcode!(b, d, d, p,); } // n_byte_opcode(input, 11), //: 11 bytes (1 + 1 + 4 + 4 + 1)
-		0xAE => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xB1 => { expand_opcode!(w, w, p,); } // n_byte_opcode(input, 6), //: 6 bytes (1 + 2 + 2 + 1)
-		0xB2 => { expand_opcode!(b, p, s,); } // make_string_opcode(3, input), //: Variable (minimum 4 bytes + string length)
-		0xB3 => { expand_opcode!(p, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0xB4 => { expand_opcode!(p, p, w, w, d, b, p,); } // n_byte_opcode(input, 14), //: 14 bytes (1 + 1 + 1 + 2 + 2 + 4 + 1 + 1)
-		0xB5 => { expand_opcode!(b, b, p, p, p, p, p,); } // n_byte_opcode(input, 8), //: 8 bytes (1 + 1 + 1 + 1 + 1 + 1 + 1 + 1)
-		0xB6 => { expand_opcode!(w, s,); } // make_string_opcode(3, input), //: Variable (minimum 4 bytes + string length)
-		0xB7 => { expand_opcode!(b, w, w, s,); } // make_string_opcode(6, input), //: Variable (minimum 7 bytes + string length)
-		0xB8 => { expand_opcode!(b, b, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 1 + 1 + 1)
-		0xB9 => { expand_opcode!(b, b, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 1 + 1 + 1)
-		0xBA => { expand_opcode!(w, w, b, b, b, b, b, w, s,); } // make_string_opcode(12, input), //: Variable (minimum 13 bytes + string length)
-		0xBB => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xBC => { expand_opcode!(b, b, b, p,); } // n_byte_opcode(input, 5), //: 5 bytes (1 + 1 + 1 + 1 + 1)
-		0xBD => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0xBE => { expand_opcode!(b, b, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 1 + 1 + 1)
-		0xBF => { expand_opcode!(b, b, b, w, p,); } // n_byte_opcode(input, 7), //: 7 bytes (1 + 1 + 1 + 1 + 2 + 1)
-		0xE0 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0xE2 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xE3 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xE4 => { expand_opcode!(b, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0xE5 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xE6 => { expand_opcode!(p, p,); } // n_byte_opcode(input, 3), //: 3 bytes (1 + 1 + 1)
-		0xE7 => { expand_opcode!(w, p,); } // n_byte_opcode(input, 4), //: 4 bytes (1 + 2 + 1)
-		0xE8 => { expand_opcode!(s,); } // make_string_opcode(1, input), //: Variable (minimum 2 bytes + string length)
-		0xE9 => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xEA => { expand_opcode!(b, s,); } // make_string_opcode(2, input), //: Variable (minimum 3 bytes + string length)
-		0xEB => { expand_opcode!(p,); } // n_byte_opcode(input, 2), //: 2 bytes (1 + 1)
-		0xFF => expand_opcode!(), // n_byte_opcode(input, 1), //: 1 byte
-		_ => {
-			log::error!("Unknown opcode 0x{:02X}", &input[0]);
-			return None;
+	for field in spec.fields {
+		match field.kind {
+			FieldKind::Byte => {
+				fields.push(OpField::Byte(*input.get(ptr).ok_or(truncated)?));
+				ptr += 1;
+			}
+			FieldKind::Word => {
+				fields.push(OpField::Word(transmute_to_u16(ptr, input.get(..ptr + 2).ok_or(truncated)?)));
+				ptr += 2;
+			}
+			FieldKind::DWord | FieldKind::Jump | FieldKind::RelativeJump => {
+				fields.push(OpField::DWord(transmute_to_u32(ptr, input.get(..ptr + 4).ok_or(truncated)?)));
+				ptr += 4;
+			}
+			FieldKind::String => {
+				let (len, string) = read_string(ptr, input).ok_or(truncated)?;
+				fields.push(OpField::String(string));
+				ptr += len;
+			}
+			FieldKind::Choices => {
+				let n_choices = fields.iter().find_map(OpField::as_byte).unwrap_or_default();
+				let mut choices = vec![];
+				for _ in 0..n_choices {
+					let choice = make_choice(input.get(ptr..).ok_or(truncated)?).ok_or(truncated)?;
+					ptr += choice.size();
+					choices.push(choice);
+				}
+				fields.push(OpField::Choice(choices));
+			}
+			FieldKind::Padding => {
+				input.get(ptr).ok_or(truncated)?;
+				fields.push(OpField::Padding(1));
+				ptr += 1;
+			}
 		}
-	};
+	}
 
 	log::debug!("final pointer value: {ptr}");
-	Some(Opcode { opcode: input[0], address: addr, actual_address: addr, fields })
-}
\ No newline at end of file
+	Ok(Opcode { opcode, address: addr, actual_address: addr, label: None, fields })
+}
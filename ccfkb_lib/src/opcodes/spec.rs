@@ -0,0 +1,345 @@
+//! The layout of every opcode, declared once. The decoder, the encoder and [`Instruction`] are all driven by the
+//! table at the bottom of this file, so a layout fix only has to be made there.
+
+use crate::opcodes::layouts::layout_override;
+use crate::opcodes::{Choice, JumpTarget, OpField, Opcode, TLString};
+
+/// The kind of a single opcode field, as written in the table.
+///
+/// - `b`, `w`, `d`: 1, 2 and 4 byte little endian integers.
+/// - `s`: a NUL terminated Shift-JIS string.
+/// - `c`: a list of choices, as many as the first byte field says.
+/// - `p`: a padding byte, always 0.
+/// - `j`: a 4 byte jump target, the address of an instruction in the same script.
+/// - `r`: a 4 byte jump target, relative to the end of the instruction holding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+	Byte,
+	Word,
+	DWord,
+	String,
+	Choices,
+	Padding,
+	Jump,
+	RelativeJump,
+}
+
+impl FieldKind {
+	pub fn code(&self) -> &'static str {
+		match self {
+			FieldKind::Byte => "b",
+			FieldKind::Word => "w",
+			FieldKind::DWord => "d",
+			FieldKind::String => "s",
+			FieldKind::Choices => "c",
+			FieldKind::Padding => "p",
+			FieldKind::Jump => "j",
+			FieldKind::RelativeJump => "r",
+		}
+	}
+
+	pub fn from_code(code: &str) -> Option<Self> {
+		match code {
+			"b" => Some(FieldKind::Byte),
+			"w" => Some(FieldKind::Word),
+			"d" => Some(FieldKind::DWord),
+			"s" => Some(FieldKind::String),
+			"c" => Some(FieldKind::Choices),
+			"p" => Some(FieldKind::Padding),
+			"j" => Some(FieldKind::Jump),
+			"r" => Some(FieldKind::RelativeJump),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+	pub name: &'static str,
+	pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OpcodeSpec {
+	pub opcode: u8,
+	pub mnemonic: &'static str,
+	pub doc: &'static str,
+	pub fields: &'static [FieldSpec],
+}
+
+impl OpcodeSpec {
+	/// An opcode with this layout and made up field values, for checking tools against every layout. Choice counts
+	/// are 2, strings hold text that needs escaping in most formats, and jumps hold 0: the start of the script for an
+	/// absolute jump, the next instruction for a relative one.
+	pub fn sample(&self, address: usize) -> Opcode {
+		let text = |raw: &str| TLString { raw: raw.to_string(), translation: None, notes: None };
+		let has_choices = self.fields.iter().any(|it| it.kind == FieldKind::Choices);
+
+		let mut fields = vec![];
+		for field in self.fields {
+			fields.push(match field.kind {
+				FieldKind::Byte if has_choices && !fields.iter().any(|it| matches!(it, OpField::Byte(_))) => OpField::Byte(2),
+				FieldKind::Byte => OpField::Byte(0x07),
+				FieldKind::Word => OpField::Word(0x1234),
+				FieldKind::DWord => OpField::DWord(0x00AB_CDEF),
+				FieldKind::String => OpField::String(text("「テスト」 \"quoted\" back\\slash ; not a comment")),
+				FieldKind::Choices => OpField::Choice(
+					["はい", "いいえ, {maybe}"]
+						.iter()
+						.map(|it| Choice { arg1: 0x0001, choice_str: text(it), trailer: vec![0; 11] })
+						.collect(),
+				),
+				FieldKind::Padding => OpField::Padding(1),
+				FieldKind::Jump | FieldKind::RelativeJump => OpField::DWord(0),
+			});
+		}
+
+		Opcode { opcode: self.opcode, address, actual_address: address, label: None, fields }
+	}
+}
+
+/// The layout of `opcode`, preferring one loaded with [`set_opcode_layouts`](crate::opcodes::set_opcode_layouts).
+pub fn opcode_spec(opcode: u8) -> Option<&'static OpcodeSpec> {
+	layout_override(opcode).or_else(|| OPCODE_SPECS.iter().find(|it| it.opcode == opcode))
+}
+
+/// The layout of every known opcode in opcode order, with loaded layouts in place of built-in ones.
+pub fn opcode_specs() -> impl Iterator<Item = &'static OpcodeSpec> {
+	(0..=0xFF).filter_map(opcode_spec)
+}
+
+macro_rules! field_kind {
+	(b) => { FieldKind::Byte };
+	(w) => { FieldKind::Word };
+	(d) => { FieldKind::DWord };
+	(s) => { FieldKind::String };
+	(c) => { FieldKind::Choices };
+	(p) => { FieldKind::Padding };
+	(j) => { FieldKind::Jump };
+	(r) => { FieldKind::RelativeJump };
+}
+
+macro_rules! field_type {
+	(b) => { u8 };
+	(w) => { u16 };
+	(d) => { u32 };
+	(s) => { TLString };
+	(c) => { Vec<Choice> };
+	(p) => { () };
+	(j) => { JumpTarget };
+	(r) => { JumpTarget };
+}
+
+macro_rules! field_value {
+	(b, $field:expr) => { match $field { OpField::Byte(it) => Some(*it), _ => None } };
+	(w, $field:expr) => { match $field { OpField::Word(it) => Some(*it), _ => None } };
+	(d, $field:expr) => { match $field { OpField::DWord(it) => Some(*it), _ => None } };
+	(s, $field:expr) => { match $field { OpField::String(it) => Some(it.clone()), _ => None } };
+	(c, $field:expr) => { match $field { OpField::Choice(it) => Some(it.clone()), _ => None } };
+	(p, $field:expr) => { match $field { OpField::Padding(_) => Some(()), _ => None } };
+	(j, $field:expr) => { JumpTarget::from_field($field) };
+	(r, $field:expr) => { JumpTarget::from_field($field) };
+}
+
+macro_rules! to_field {
+	(b, $value:expr) => { OpField::Byte(*$value) };
+	(w, $value:expr) => { OpField::Word(*$value) };
+	(d, $value:expr) => { OpField::DWord(*$value) };
+	(s, $value:expr) => { OpField::String($value.clone()) };
+	(c, $value:expr) => { OpField::Choice($value.clone()) };
+	(p, $value:expr) => { { let () = *$value; OpField::Padding(1) } };
+	(j, $value:expr) => { $value.to_field() };
+	(r, $value:expr) => { $value.to_field() };
+}
+
+macro_rules! opcode_table {
+	($($opcode:literal $variant:ident $mnemonic:ident $doc:literal { $($field:ident: $kind:ident),* $(,)? })*) => {
+		pub static OPCODE_SPECS: &[OpcodeSpec] = &[$(
+			OpcodeSpec {
+				opcode: $opcode,
+				mnemonic: stringify!($mnemonic),
+				doc: $doc,
+				fields: &[$(FieldSpec { name: stringify!($field), kind: field_kind!($kind) }),*],
+			}
+		),*];
+
+		/// A decoded opcode with named fields. Padding fields are kept as `()` so every field of the table has a
+		/// place, which lets callers write `Text { text, .. }`.
+		#[derive(Clone)]
+		pub enum Instruction {
+			$(
+				#[doc = $doc]
+				$variant { $($field: field_type!($kind)),* },
+			)*
+		}
+
+		impl Instruction {
+			pub fn opcode(&self) -> u8 {
+				match self {
+					$(Instruction::$variant { .. } => $opcode,)*
+				}
+			}
+
+			pub fn mnemonic(&self) -> &'static str {
+				match self {
+					$(Instruction::$variant { .. } => stringify!($mnemonic),)*
+				}
+			}
+
+			/// Reads the fields of `opcode` into their named places. Returns `None` for unknown opcodes and for
+			/// fields that do not match the table.
+			pub fn from_opcode(opcode: &Opcode) -> Option<Self> {
+				match opcode.opcode {
+					$($opcode => {
+						#[allow(unused_mut, unused_variables)]
+						let mut fields = opcode.fields.iter();
+						Some(Instruction::$variant { $($field: field_value!($kind, fields.next()?)?),* })
+					})*
+					_ => None,
+				}
+			}
+
+			pub fn to_fields(&self) -> Vec<OpField> {
+				match self {
+					$(Instruction::$variant { $($field),* } => vec![$(to_field!($kind, $field)),*],)*
+				}
+			}
+
+			pub fn to_opcode(&self, address: usize) -> Opcode {
+				Opcode { opcode: self.opcode(), address, actual_address: address, label: None, fields: self.to_fields() }
+			}
+		}
+	};
+}
+
+opcode_table! {
+	0x01 Branch branch "Conditional jump by a relative offset from the end of the instruction. Branch types 1 to 6 are GE, LE, EQ, NE, GT and LT; `arg2` is a variable if bit 5 of the branch type is set." { branch_type: b, arg1: w, arg2: w, offset: r, pad: p }
+	0x02 Choices choices "Shows a choice." { n_choices: b, pad: p, choices: c }
+	0x03 VarOp var_op "Load and store operations on the variable heap, like addition, subtraction and assigning random values." { op_type: b, var: w, arg2: b, arg3: w, pad: p }
+	0x04 Wait wait "Does not advance the instruction pointer until certain conditions are met." {}
+	0x05 Op05 op_05 "Unknown." { arg1: b, pad: p }
+	0x06 Jump jump "Unconditional jump to an absolute offset within the current script." { target: j, pad: p }
+	0x07 CallScript call_script "Goes to the named script, not fully sure of the difference with 0x09." { arg1: w, script: s }
+	0x08 Nop nop "Does nothing." { pad: p }
+	0x09 GotoScript goto_script "Goes to the named script." { script: s }
+	0x0A Return ret "Returns." { pad: p }
+	0x0B Op0B op_0b "Unknown." { arg1: b, pad: p }
+	0x0C Op0C op_0c "Unknown." { arg1: w, pad: p }
+	0x0D Op0D op_0d "Debug print? One path outputs the current opcode." { arg1: w, arg2: w, arg3: w, pad: p }
+	0x0E Op0E op_0e "Unknown." { arg1: b, pad: p }
+	0x21 Music music "Loops the music file `file`.ogg." { arg1: b, arg2: w, arg3: b, arg4: w, arg5: d, file: s }
+	0x22 Op22 op_22 "Some file operation?" { arg1: b, arg2: w, pad: p }
+	0x23 Voice voice "Plays the character voice file `file`.ogg." { arg1: b, arg2: w, arg3: w, arg4: w, arg5: b, arg6: b, file: s }
+	0x24 Op24 op_24 "Lots of sleep timers." { pad: p }
+	0x25 SoundEffect sound_effect "Plays the sound effect file `file`." { arg1: b, arg2: b, arg3: w, pad: p, pad2: p, arg4: b, arg5: w, arg6: b, file: s }
+	0x26 Op26 op_26 "Unknown." { arg1: b, pad: p }
+	0x27 Voice2 voice2 "Plays the character voice file `file`.ogg, the engine treats it differently from 0x23 in some way." { arg1: b, arg2: w, arg3: w, arg4: w, arg5: b, arg6: b, file: s }
+	0x28 Op28 op_28 "Unknown." { arg1: b, arg2: b, pad: p, pad2: p, pad3: p }
+	0x29 Op29 op_29 "Unknown." { arg1: b, arg2: w, pad: p, pad2: p }
+	0x30 Op30 op_30 "Unknown." { arg1: b, pad: p, pad2: p, pad3: p }
+	0x31 Op31 op_31 "Unknown." { arg1: b, pad: p }
+	0x32 Op32 op_32 "Unknown." { arg1: b, pad: p }
+	0x33 Op33 op_33 "Unknown." { arg1: w, arg2: w, arg3: w, pad: p }
+	0x34 Op34 op_34 "Unknown." { arg1: w, arg2: b, arg3: b, arg4: s }
+	0x41 Text text "Writes textbox content, without a speaker." { arg1: w, arg2: b, arg3: b, text: s }
+	0x42 TextWithSpeaker text_with_speaker "Writes textbox content, with a speaker." { arg1: w, arg2: b, arg3: b, arg4: b, speaker: s, text: s }
+	0x43 LoadFile load_file "Loads the file `file`." { arg1: b, arg2: w, arg3: w, arg4: b, file: s }
+	0x44 Op44 op_44 "Unknown." { arg1: b, arg2: b, arg3: b, pad: p }
+	0x45 Op45 op_45 "Unknown." { arg1: b, arg2: b, arg3: b, pad: p }
+	0x46 LoadImage load_image "Loads the image `file`.png." { arg1: w, arg2: w, pad: p, pad2: p, pad3: p, arg3: b, arg4: b, file: s }
+	0x47 Op47 op_47 "Unknown." { arg1: b, pad: p }
+	0x48 Op48 op_48 "Unknown." { arg1: b, arg2: w, arg3: w, arg4: d, arg5: b, arg6: b, arg7: s }
+	0x49 Op49 op_49 "`arg1` seems to be a count of some kind." { arg1: w, pad: p }
+	0x4A Op4A op_4a "Possibly displays the loaded image with a fade in effect." { arg1: b, arg2: w, arg3: w, pad: p }
+	0x4B Op4B op_4b "Unknown." { arg1: b, arg2: w, arg3: w, arg4: d, arg5: w, arg6: d, arg7: d, pad: p }
+	0x4C Op4C op_4c "Unknown." { arg1: b, arg2: b, arg3: d, pad: p, pad2: p }
+	0x4D Op4D op_4d "Unknown." { arg1: b, arg2: b, arg3: w, arg4: w, arg5: w, arg6: w, arg7: w, pad: p }
+	0x4E Op4E op_4e "Unknown." { arg1: b, arg2: b, arg3: b, pad: p }
+	0x4F Op4F op_4f "Unknown." { arg1: b, arg2: b, arg3: b, pad: p }
+	0x50 LoadTable load_table "Loads the tbl file `file`, seems unused." { file: s }
+	0x51 Op51 op_51 "Unknown." { arg1: w, arg2: w, pad: p }
+	0x52 Op52 op_52 "Unknown." { arg1: b, pad: p }
+	0x53 PrintMessage print_message "Prints `text` in the message window." { arg1: b, arg2: w, arg3: w, text: s }
+	0x54 LoadMsk load_msk "Loads the msk file `file`." { file: s }
+	0x55 Op55 op_55 "Unknown." { pad: p }
+	0x56 Op56 op_56 "Unknown." { pad: p }
+	0x57 Op57 op_57 "Unknown." { arg1: w, arg2: w, arg3: d, pad: p }
+	0x58 Op58 op_58 "Unknown." { arg1: b, arg2: b, arg3: b, arg4: w, arg5: w, pad: p }
+	0x59 LoadWip load_wip "Loads the wip file `file`." { file: s }
+	0x60 Op60 op_60 "Unknown." { pad: p }
+	0x61 LoadMovie load_movie "Loads the movie file `file`." { arg1: b, file: s }
+	0x62 Op62 op_62 "Unknown." { pad: p }
+	0x63 Op63 op_63 "Unknown." { arg1: b, arg2: b, pad: p }
+	0x64 Op64 op_64 "Unknown." { arg1: b, arg2: w, arg3: w, arg4: w, pad: p }
+	0x65 Op65 op_65 "Unknown." { arg1: w, arg2: w, pad: p }
+	0x66 Op66 op_66 "No terminator, maybe for showing an inlay?" { arg1: b, arg2: w, arg3: w, arg4: b, arg5: w, arg6: d, arg7: w, arg8: d, arg9: d }
+	0x67 Op67 op_67 "Unknown." { arg1: b, arg2: b, pad: p, arg3: d, pad2: p }
+	0x68 Op68 op_68 "Unknown." { arg1: w, arg2: w, arg3: w, arg4: w, pad: p }
+	0x69 Op69 op_69 "Unknown." { arg1: b, pad: p }
+	0x70 Op70 op_70 "Unknown." { arg1: b, arg2: b, pad: p, arg3: d, pad2: p }
+	0x71 Op71 op_71 "Unknown." { arg1: s }
+	0x72 Op72 op_72 "Unknown." { pad: p }
+	0x73 Op73 op_73 "Unknown." { arg1: w, arg2: w, arg3: d, arg4: b, arg5: s }
+	0x74 Op74 op_74 "Unknown." { arg1: b, pad: p }
+	0x75 Op75 op_75 "Unknown." { arg1: w, arg2: w, arg3: w, arg4: w, pad: p }
+	0x76 Op76 op_76 "Unknown." { arg1: w, arg2: w, arg3: d, arg4: b, arg5: b, arg6: w, arg7: d, pad: p }
+	0x77 Op77 op_77 "Unknown." { arg1: w, arg2: w, arg3: d, pad: p }
+	0x78 Op78 op_78 "Unknown." { arg1: b, arg2: b, arg3: b, arg4: d, pad: p }
+	0x79 Op79 op_79 "Unknown." { pad: p }
+	0x81 Op81 op_81 "Unknown." { pad: p, pad2: p }
+	0x82 Op82 op_82 "Unknown." { arg1: w, pad: p }
+	0x83 Op83 op_83 "Unknown." { pad: p }
+	0x84 Op84 op_84 "Unknown." { pad: p }
+	0x85 Op85 op_85 "Unknown." { arg1: b, pad: p }
+	0x86 Op86 op_86 "Unknown." { pad: p, pad2: p }
+	0x87 Op87 op_87 "Unknown." { arg1: w, pad: p }
+	0x88 Op88 op_88 "Unknown." { pad: p, pad2: p, pad3: p }
+	0x89 Op89 op_89 "Unknown." { pad: p }
+	0x8A Op8A op_8a "Unknown." { pad: p }
+	0x8B Op8B op_8b "Unknown." { pad: p }
+	0x8C Op8C op_8c "Unknown." { arg1: w, pad: p }
+	0x8D Op8D op_8d "Unknown." { pad: p }
+	0x8E Op8E op_8e "Unknown." { pad: p }
+	0xA0 OpA0 op_a0 "Unknown." { arg1: w, arg2: w, arg3: b, pad: p }
+	0xA1 OpA1 op_a1 "Unknown." { arg1: b, arg2: w, arg3: w, arg4: b, pad: p }
+	0xA2 OpA2 op_a2 "Unknown." { arg1: b, arg2: w, arg3: w, pad: p }
+	0xA3 OpA3 op_a3 "Unknown." { pad: p, arg1: w, arg2: w, pad2: p }
+	0xA4 OpA4 op_a4 "Unknown." { pad: p, arg1: w, arg2: w, pad2: p }
+	0xA5 OpA5 op_a5 "Unknown." { arg1: b, pad: p }
+	0xA6 OpA6 op_a6 "Unknown." { pad: p }
+	0xA7 OpA7 op_a7 "Unknown." { pad: p }
+	0xA8 OpA8 op_a8 "Unknown." { arg1: b, arg2: b, arg3: b, pad: p, pad2: p, pad3: p, pad4: p, arg4: w, arg5: w, arg6: w, arg7: w, pad5: p }
+	0xA9 OpA9 op_a9 "Unknown." { pad: p }
+	0xAA OpAA op_aa "Unknown." { arg1: b, arg2: b, pad: p }
+	0xAB OpAB op_ab "Unknown." { pad: p }
+	0xAC OpAC op_ac "Unknown." { pad: p }
+	0xAD OpAD op_ad "Unknown." { arg1: b, arg2: d, arg3: d, pad: p }
+	0xAE OpAE op_ae "Unknown." { pad: p }
+	0xB1 OpB1 op_b1 "Unknown." { arg1: w, arg2: w, pad: p }
+	0xB2 LoadEffect load_effect "Loads the effect file `file`." { arg1: b, pad: p, file: s }
+	0xB3 OpB3 op_b3 "Unknown." { pad: p, pad2: p }
+	0xB4 OpB4 op_b4 "Unknown." { pad: p, pad2: p, arg1: w, arg2: w, arg3: d, arg4: b, pad3: p }
+	0xB5 OpB5 op_b5 "Unknown." { arg1: b, arg2: b, pad: p, pad2: p, pad3: p, pad4: p, pad5: p }
+	0xB6 OpB6 op_b6 "Unknown." { arg1: w, arg2: s }
+	0xB7 OpB7 op_b7 "Loads a wip or msk file?" { arg1: b, arg2: w, arg3: w, file: s }
+	0xB8 OpB8 op_b8 "Unknown." { arg1: b, arg2: b, pad: p }
+	0xB9 OpB9 op_b9 "Unknown." { arg1: b, arg2: b, pad: p }
+	0xBA OpBA op_ba "Unknown." { arg1: w, arg2: w, arg3: b, arg4: b, arg5: b, arg6: b, arg7: b, arg8: w, arg9: s }
+	0xBB OpBB op_bb "Unknown." { pad: p }
+	0xBC OpBC op_bc "Unknown." { arg1: b, arg2: b, arg3: b, pad: p }
+	0xBD OpBD op_bd "Unknown." { arg1: b, pad: p }
+	0xBE OpBE op_be "Unknown." { arg1: b, arg2: b, pad: p }
+	0xBF OpBF op_bf "Unknown." { arg1: b, arg2: b, arg3: b, arg4: w, pad: p }
+	0xE0 SceneTitle scene_title "Sets the scene title." { title: s }
+	0xE2 OpE2 op_e2 "Unknown." { pad: p }
+	0xE3 OpE3 op_e3 "Unknown." { pad: p }
+	0xE4 OpE4 op_e4 "Unknown." { arg1: b, pad: p }
+	0xE5 OpE5 op_e5 "Unknown." { pad: p }
+	0xE6 OpE6 op_e6 "Unknown." { pad: p, pad2: p }
+	0xE7 OpE7 op_e7 "Unknown." { arg1: w, pad: p }
+	0xE8 OpE8 op_e8 "Unknown." { arg1: s }
+	0xE9 OpE9 op_e9 "Unknown." { pad: p }
+	0xEA OpEA op_ea "Does something with a wip or msk file." { arg1: b, file: s }
+	0xEB OpEB op_eb "Unknown." { pad: p }
+	0xFF End end "End of the script." {}
+}
@@ -221,102 +221,3 @@ pub fn to_bytes<T: Sized>(value: &T) -> &[u8] {
 		&*core::ptr::slice_from_raw_parts(data, size_of_val(value))
 	}
 }
-
-// unsigned long CrossChannelCrack::unwipf( unsigned char* buff,      // 输入文件正文的array
-// 										 unsigned long  len,       // 输入文件长度
-// 										 unsigned char* out_buff,  // 输出文件正文的array
-// 										 unsigned long  out_len )  // 输出文件长度
-// {
-pub fn unwipf(input: &[u8], out_len: usize) -> Vec<u8> {
-	let mut buff = 0usize;
-
-	// 	unsigned long  ring_len   = 4096;                        // 寻址范围就是0-4095
-	// 	unsigned char* ring       = new unsigned char[ring_len]; // 剪贴板
-	let mut ring = [0u8; 4096];
-	// //	unsigned long ring_index  = 0xFEE;                       // 剪贴板的索引
-	// 	unsigned long  ring_index = 1;                           // 剪贴板的索引
-	let mut ring_index = 0xFEEusize;
-	// 	unsigned char* end        = buff + len;                  // 指向输入array的末尾
-	let end = input.len();
-
-	let mut out = vec![0u8; out_len];
-	let mut out_buff = 0usize;
-	// 	unsigned char* out_end    = out_buff + out_len;          // 指向输出array的末尾
-	let out_end = out.len();
-
-	// 	memset(ring, 0, ring_len); // 剪贴板 置零
-
-	// 	while (buff < end && out_buff < out_end) { // 判断条件：不超过array的末尾
-	while buff < end && out_buff < out_end {
-		// 		unsigned char flags = *buff++; //<1> 取当前字符存到flags里面
-		let mut flags = input[buff];
-		buff += 1;
-		// 		// 这个循环是判断8位的char型的每一位是否为1
-		// 		for (int i = 0; i < 8 && buff < end && out_buff < out_end; i++) { // 判断条件：char型长度8循环&&不超过array的末尾
-		for _ in 0..8 {
-			if buff >= end || out_buff >= out_end {
-				break;
-			}
-			// 			if (flags & 0x01) { // 判断：最低位是否为1
-			if flags & 1 == 1 {
-				// 			/*
-				// 			*out_buff = ring[ring_index % ring_len] = *buff++; // 从ring[ 1 ]开始赋值到[ 0 ]，以4096循环
-				out[out_buff] = ring[ring_index % 4096];
-				// 			ring_index++;
-				ring_index += 1;
-				// 			*out_buff++;
-				out_buff += 1;
-			// 			*/
-			// 		    *out_buff++ = ring[ring_index++ % ring_len] = *buff++; //<2>
-			// 			} else {
-			} else {
-				// 				if (end - buff < 2) break;
-				if (end - buff) < 2 {
-					break;
-				}
-				// 				unsigned long p = *buff++; //<3>
-				let p = input[buff] as u64;
-				buff += 1;
-				// 				unsigned long n = *buff++; //<4>
-				let n = input[buff] as u64;
-				buff += 1;
-				// 				//p = p | ( ( n >> 4 ) << 8 );
-				// 				p = (p << 4) | (n >> 4);   // ( RHL p, 4 ) OR ( RHR n, 4 ) 即 p的八位 与 n的前四位 构成
-				let mut p1 = (p << 4) | (n >> 4);
-				// 				n = (n & 0x0F) + 2;        // AND 00001111b; ADD 2 这里用到的是n的后四位
-				let n1 = (n & 0x0F) + 2;
-				// 				for (unsigned long j = 0; j < n && out_buff < out_end; j++) {
-				for _ in 0..n1 {
-					if out_buff >= out_end {
-						break;
-					}
-					// 				//for (unsigned long j = 0; j <= n && out_buff < out_end; j++) {
-					// 					/*
-					// 					*out_buff = ring[ring_index % ring_len] = ring[p % ring_len];
-					ring[ring_index % 4096] = ring[(p1 as usize) % 4096];
-					out[out_buff] = ring[ring_index % 4096];
-					// 					p++;
-					p1 += 1;
-					// 					ring_index++;
-					ring_index += 1;
-					// 					*out_buff++;
-					out_buff += 1;
-					// 					*/
-					// 					*out_buff++ = ring[ring_index++ % ring_len] = ring[p++ % ring_len];
-					// 				}
-				}
-				// 			}
-			}
-			// 			flags >>= 1; // 右移1位，进入下一轮判断
-			flags >>= 1;
-			// 		}
-		}
-		// 	}
-	}
-
-	out
-	// 	delete [] ring;
-
-	// 	return out_len - (out_end - out_buff); // 忽略掉的字符数量
-	// }
-}
\ No newline at end of file
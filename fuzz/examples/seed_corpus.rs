@@ -0,0 +1,81 @@
+//! Writes a seed corpus of synthetic files for every fuzz target to `fuzz/corpus`, built from the opcode table.
+//!
+//! Run with `cargo run --example seed_corpus` from `fuzz`.
+
+use camino::Utf8PathBuf;
+use ccfkb_lib::data::asm::sample_script;
+use ccfkb_lib::data::text_script::tl_transform_script;
+use ccfkb_lib::data::wipf::{encode_wipf, WipfImage};
+use ccfkb_lib::data::{write_arc, ExtensionDescriptor, FileDescriptor};
+use ccfkb_lib::opcodes::{opcode_specs, Script};
+
+fn write(target: &str, name: &str, contents: &[u8]) {
+	let dir = Utf8PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("corpus").join(target);
+	std::fs::create_dir_all(&dir).unwrap();
+	std::fs::write(dir.join(name), contents).unwrap();
+}
+
+fn sample_images() -> Vec<(u16, Vec<WipfImage>)> {
+	let image = |depth: u16, width: u32, height: u32| WipfImage {
+		width,
+		height,
+		x_offset: 1,
+		y_offset: 2,
+		unk_layer: 0,
+		depth,
+		palette: if depth == 8 { (0..1024).map(|it| it as u8).collect() } else { vec![] },
+		pixels: (0..width * height * (depth as u32 / 8)).map(|it| (it % 7) as u8).collect(),
+	};
+	vec![(8, vec![image(8, 4, 3), image(8, 2, 2)]), (24, vec![image(24, 3, 3)])]
+}
+
+fn main() {
+	let script = sample_script();
+	let wsc = script.binary_serialise().unwrap();
+
+	for spec in opcode_specs() {
+		let opcode = Script { opcodes: vec![spec.sample(0)], trailer: vec![] }.binary_serialise().unwrap();
+		write("make_opcode", &format!("{:02X}_{}", spec.opcode, spec.mnemonic), &opcode);
+		if spec.opcode == 0x02 {
+			// make_choice takes the bytes after the opcode byte and its padding.
+			let mut choices = vec![opcode[1]];
+			choices.extend(&opcode[3..]);
+			write("make_choice", "sample", &choices);
+		}
+	}
+
+	write("decode_wsc", "sample", &wsc);
+	write("decode_wsc", "truncated", &wsc[..wsc.len() / 2]);
+	write("parse_doclines", "sample", tl_transform_script(&script).as_bytes());
+
+	let mut wipfs = vec![];
+	for (depth, images) in sample_images() {
+		let wipf = encode_wipf(depth, &images);
+		write("decode_wipf", &format!("depth_{depth}"), &wipf);
+		wipfs.push(wipf);
+	}
+	// A 24 bit entry 0 pixels wide, which used to panic while de-interleaving its planes.
+	let mut zero_width = encode_wipf(24, &sample_images()[1].1[..1]);
+	zero_width[8..12].copy_from_slice(&0u32.to_le_bytes());
+	write("decode_wipf", "zero_width", &zero_width);
+
+	// write_arc reads the files to pack from disk.
+	let temp = Utf8PathBuf::from_path_buf(std::env::temp_dir()).unwrap().join("ccfkb_seed_corpus");
+	std::fs::create_dir_all(&temp).unwrap();
+	let mut paths = vec![temp.join("SAMPLE.WSC")];
+	std::fs::write(&paths[0], &wsc).unwrap();
+	let mut files = vec![FileDescriptor { name: "SAMPLE".to_string(), size: wsc.len(), offset: 0 }];
+	for (idx, wipf) in wipfs.iter().enumerate() {
+		let name = format!("IMAGE{idx}");
+		paths.push(temp.join(format!("{name}.WIP")));
+		std::fs::write(paths.last().unwrap(), wipf).unwrap();
+		files.push(FileDescriptor { name, size: wipf.len(), offset: 0 });
+	}
+	// Each extension takes 4 bytes of name and 8 of counts, after the 4 byte count of extensions.
+	let extensions = vec![
+		ExtensionDescriptor { name: "WSC".to_string(), number: 1, offset: 28 },
+		ExtensionDescriptor { name: "WIP".to_string(), number: wipfs.len() as u32, offset: 28 + 21 },
+	];
+	write("read_arc", "sample", &write_arc(&paths, extensions, files));
+	std::fs::remove_dir_all(&temp).unwrap();
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use ccfkb_lib::data::wipf::decode_wipf;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = decode_wipf("fuzz", data);
+});
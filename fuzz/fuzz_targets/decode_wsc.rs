@@ -0,0 +1,8 @@
+#![no_main]
+
+use ccfkb_lib::data::decode_wsc;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = decode_wsc(data);
+});
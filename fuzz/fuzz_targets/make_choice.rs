@@ -0,0 +1,15 @@
+#![no_main]
+
+use ccfkb_lib::opcodes::make_opcode;
+use libfuzzer_sys::fuzz_target;
+
+// Choices are only read as part of a 0x02, so the input is the choice count followed by the choices.
+fuzz_target!(|data: &[u8]| {
+	let Some((n_choices, choices)) = data.split_first() else {
+		return;
+	};
+
+	let mut input = vec![0x02, *n_choices, 0];
+	input.extend(choices);
+	let _ = make_opcode(&input, 0);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use ccfkb_lib::opcodes::make_opcode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = make_opcode(data, 0);
+});
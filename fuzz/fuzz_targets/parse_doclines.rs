@@ -0,0 +1,8 @@
+#![no_main]
+
+use ccfkb_lib::data::text_script::parse_doclines;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+	let _ = parse_doclines(data);
+});
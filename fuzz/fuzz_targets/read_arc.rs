@@ -0,0 +1,11 @@
+#![no_main]
+
+use camino::Utf8Path;
+use ccfkb_lib::data::read_arc;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let mut data = data.to_vec();
+	// Nothing is written without a WIPF layout.
+	let _ = read_arc(&mut data, Utf8Path::new("."), None);
+});
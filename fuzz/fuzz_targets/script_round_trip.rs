@@ -0,0 +1,80 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ccfkb_lib::data::verify::verify_wsc;
+use ccfkb_lib::opcodes::{opcode_specs, Choice, FieldKind, OpField, Opcode, OpcodeSpec, Script, TLString};
+use libfuzzer_sys::fuzz_target;
+
+/// Characters strings are made of. All of them encode to Shift JIS and none are control characters, which real
+/// scripts do not hold either.
+const ALPHABET: [char; 12] = ['a', 'Z', '0', ' ', '"', '\\', '%', 'あ', '漢', '「', '」', '、'];
+
+#[derive(Arbitrary, Debug)]
+struct FuzzChoice {
+	arg1: u16,
+	text: Vec<u8>,
+	trailer: [u8; 11],
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzOpcode {
+	spec: u8,
+	values: Vec<u32>,
+	texts: Vec<Vec<u8>>,
+	choices: Vec<FuzzChoice>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzScript {
+	opcodes: Vec<FuzzOpcode>,
+	trailer: Vec<u8>,
+}
+
+fn text(indices: &[u8]) -> TLString {
+	let raw = indices.iter().map(|it| ALPHABET[*it as usize % ALPHABET.len()]).collect();
+	TLString { raw, translation: None, notes: None }
+}
+
+/// An opcode with the layout of `spec` and field values from `input`, like [`OpcodeSpec::sample`].
+fn build_opcode(spec: &OpcodeSpec, input: &FuzzOpcode) -> Opcode {
+	let mut values = input.values.iter().copied().chain(std::iter::repeat(0));
+	let mut texts = input.texts.iter().map(Vec::as_slice).chain(std::iter::repeat(&[][..]));
+	let choices: Vec<Choice> = input
+		.choices
+		.iter()
+		.take(u8::MAX as usize)
+		.map(|it| Choice { arg1: it.arg1, choice_str: text(&it.text), trailer: it.trailer.to_vec() })
+		.collect();
+	let has_choices = spec.fields.iter().any(|it| it.kind == FieldKind::Choices);
+
+	let mut fields = vec![];
+	for field in spec.fields {
+		fields.push(match field.kind {
+			// The first byte of a 0x02 is its number of choices.
+			FieldKind::Byte if has_choices && !fields.iter().any(|it| matches!(it, OpField::Byte(_))) => OpField::Byte(choices.len() as u8),
+			FieldKind::Byte => OpField::Byte(values.next().unwrap() as u8),
+			FieldKind::Word => OpField::Word(values.next().unwrap() as u16),
+			FieldKind::DWord | FieldKind::Jump | FieldKind::RelativeJump => OpField::DWord(values.next().unwrap()),
+			FieldKind::String => OpField::String(text(texts.next().unwrap())),
+			FieldKind::Choices => OpField::Choice(choices.clone()),
+			FieldKind::Padding => OpField::Padding(1),
+		});
+	}
+
+	Opcode { opcode: spec.opcode, address: 0, actual_address: 0, label: None, fields }
+}
+
+// Any script made of known layouts has to encode, decode and go through YAML back to the same bytes.
+fuzz_target!(|input: FuzzScript| {
+	let specs: Vec<&OpcodeSpec> = opcode_specs().collect();
+	let opcodes: Vec<Opcode> = input.opcodes.iter().map(|it| build_opcode(specs[it.spec as usize % specs.len()], it)).collect();
+	// Scripts only have a trailer after their 0xFF, anything else would be read as more opcodes.
+	let trailer = if opcodes.last().is_some_and(|it| it.opcode == 0xFF) { input.trailer } else { vec![] };
+	let Ok(bytes) = (Script { opcodes, trailer }).binary_serialise() else {
+		return;
+	};
+
+	if let Err(error) = verify_wsc(&bytes) {
+		panic!("{error}");
+	}
+});